@@ -1,5 +1,7 @@
 /* src/all_days.rs */
 
+use std::str::FromStr;
+
 use crate::day01;
 use crate::day02;
 use crate::day03;
@@ -12,14 +14,33 @@ use crate::day09;
 use crate::day10;
 use crate::day11;
 
+/// Settings from the command line, for the days that take any.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Replacement for the day 2 part 1 bag, e.g. "12 red, 13 green, 14 blue"
+    pub bag: Option<String>,
+    /// Don't use colours in explanations
+    pub no_color: bool,
+    /// Explain as JSON, where available
+    pub json: bool,
+    /// Explain as CSV, where available
+    pub csv: bool,
+}
+
 pub fn run_day(d: usize) {
+    run_day_with(d, &Options::default());
+}
+
+pub fn run_day_with(d: usize, options: &Options) {
     match d {
         1 => {
             day01::run();
         }
-        2 => {
-            day02::run();
-        }
+        2 => match options.bag.as_deref().map(day02::BlockCollection::from_str) {
+            None => day02::run(),
+            Some(Ok(bag)) => day02::run_with_bag(&bag),
+            Some(Err(e)) => println!("Invalid bag: {}", e),
+        },
         3 => {
             day03::run();
         }
@@ -52,3 +73,27 @@ pub fn run_day(d: usize) {
         }
     }
 }
+
+pub fn explain_day(d: usize, options: &Options) {
+    match d {
+        1 => {
+            day01::run_explain(!options.no_color);
+        }
+        3 => {
+            if options.json {
+                day03::run_json();
+            } else {
+                day03::run_explain(!options.no_color);
+            }
+        }
+        4 => {
+            day04::run_trace(options.csv);
+        }
+        5 => {
+            day05::run_explain();
+        }
+        k => {
+            println!("No explain mode for day {}.", k);
+        }
+    }
+}
@@ -7,6 +7,17 @@ use std::error::Error;
 use std::fmt;
 use std::fs;
 
+pub mod aho_corasick;
+pub mod bigint;
+pub mod checked;
+pub mod interval;
+pub mod parse;
+#[cfg(test)]
+pub mod rng;
+
+pub use bigint::{BigInt, BigUint};
+pub use interval::IntervalSet;
+
 pub fn soln_output<T: fmt::Display>(day: usize, part: usize, soln: T) -> String {
     format!("Day {}, part {} solution: {}", day, part, soln)
 }
@@ -0,0 +1,142 @@
+/* src/common/aho_corasick.rs
+ *
+ * Aho-Corasick automaton for finding occurrences of several patterns at once in a single pass.
+ *
+ * Christopher Phan
+ */
+
+use std::collections::VecDeque;
+
+const ROOT: usize = 0;
+
+/// A multi-pattern matcher, working on bytes (so UTF-8 patterns only match at character
+/// boundaries).
+#[derive(Clone, Debug)]
+pub struct AhoCorasick {
+    // full transition table, so scanning is one lookup per byte
+    transitions: Vec<[usize; 256]>,
+    // ids of the patterns ending at each state, including via suffix links
+    outputs: Vec<Vec<usize>>,
+    pattern_lens: Vec<usize>,
+}
+
+/// An occurrence of pattern number `pattern` at bytes `start..end`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl AhoCorasick {
+    /// Build the automaton. Patterns are numbered in the order given; empty patterns never match.
+    pub fn new<I, P>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut trie: Vec<[Option<usize>; 256]> = vec![[None; 256]];
+        let mut outputs: Vec<Vec<usize>> = vec![vec![]];
+        let mut pattern_lens: Vec<usize> = vec![];
+        for (id, pattern) in patterns.into_iter().enumerate() {
+            let pattern = pattern.as_ref();
+            pattern_lens.push(pattern.len());
+            if pattern.is_empty() {
+                continue;
+            }
+            let mut state = ROOT;
+            for b in pattern {
+                state = match trie[state][*b as usize] {
+                    Some(next) => next,
+                    None => {
+                        trie.push([None; 256]);
+                        outputs.push(vec![]);
+                        trie[state][*b as usize] = Some(trie.len() - 1);
+                        trie.len() - 1
+                    }
+                };
+            }
+            outputs[state].push(id);
+        }
+
+        // Breadth-first, fill in the missing transitions from the failure links, and merge in the
+        // outputs of the failure state.
+        let mut transitions: Vec<[usize; 256]> = vec![[ROOT; 256]; trie.len()];
+        let mut fail: Vec<usize> = vec![ROOT; trie.len()];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        for b in 0..256 {
+            if let Some(next) = trie[ROOT][b] {
+                transitions[ROOT][b] = next;
+                queue.push_back(next);
+            }
+        }
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fail[state]].clone();
+            outputs[state].extend(inherited);
+            for b in 0..256 {
+                match trie[state][b] {
+                    Some(next) => {
+                        fail[next] = transitions[fail[state]][b];
+                        transitions[state][b] = next;
+                        queue.push_back(next);
+                    }
+                    None => {
+                        transitions[state][b] = transitions[fail[state]][b];
+                    }
+                }
+            }
+        }
+
+        Self {
+            transitions,
+            outputs,
+            pattern_lens,
+        }
+    }
+
+    /// The state before any input has been read.
+    pub fn start_state(&self) -> usize {
+        ROOT
+    }
+
+    /// The state after reading `byte` in `state`.
+    pub fn next_state(&self, state: usize, byte: u8) -> usize {
+        self.transitions[state][byte as usize]
+    }
+
+    /// The occurrences ending at byte position `end`, given the state after reading up to there.
+    pub fn matches_ending(&self, state: usize, end: usize) -> impl Iterator<Item = Match> + '_ {
+        self.outputs[state].iter().map(move |id| Match {
+            pattern: *id,
+            start: end - self.pattern_lens[*id],
+            end,
+        })
+    }
+
+    /// Every occurrence of every pattern (overlapping ones included), ordered by where they end.
+    pub fn find_overlapping_iter<'a>(
+        &'a self,
+        haystack: &'a [u8],
+    ) -> impl Iterator<Item = Match> + 'a {
+        let mut state = self.start_state();
+        haystack.iter().enumerate().flat_map(move |(pos, b)| {
+            state = self.next_state(state, *b);
+            self.matches_ending(state, pos + 1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_test() {
+        let ac = AhoCorasick::new(["he", "she", "his", "hers"]);
+        let found: Vec<(usize, usize)> = ac
+            .find_overlapping_iter(b"ushers")
+            .map(|m| (m.pattern, m.start))
+            .collect();
+        assert_eq!(found, vec![(1, 1), (0, 2), (3, 2)]);
+    }
+}
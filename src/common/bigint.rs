@@ -0,0 +1,607 @@
+/* src/common/bigint.rs
+ *
+ * Arbitrary-precision integers, for answers that don't fit in a `u64`.
+ *
+ * Christopher Phan
+ */
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::iter::{Product, Sum};
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+use std::str::FromStr;
+
+use crate::common::AdventError;
+
+/// An arbitrary-precision unsigned integer.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    // base 2^32 digits, least significant first, with no trailing zeros (so zero is empty)
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self { digits: vec![] }
+    }
+
+    pub fn one() -> Self {
+        Self::from(1_u64)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    fn from_digits(digits: Vec<u32>) -> Self {
+        let mut out_val = Self { digits };
+        out_val.trim();
+        out_val
+    }
+
+    fn trim(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.digits.len() {
+            0 => Some(0),
+            1 => Some(self.digits[0] as u64),
+            2 => Some(self.digits[0] as u64 | (self.digits[1] as u64) << 32),
+            _ => None,
+        }
+    }
+
+    /// Nearest `f64` (not necessarily correctly rounded).
+    pub fn to_f64(&self) -> f64 {
+        self.digits
+            .iter()
+            .rev()
+            .fold(0.0, |acc, k| acc * 4_294_967_296.0 + *k as f64)
+    }
+
+    fn bits(&self) -> usize {
+        match self.digits.last() {
+            Some(top) => 32 * self.digits.len() - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, k: usize) -> bool {
+        match self.digits.get(k / 32) {
+            Some(d) => (d >> (k % 32)) & 1 == 1,
+            None => false,
+        }
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+        let mut digits: Vec<u32> = Vec::with_capacity(self.digits.len());
+        let mut borrow = false;
+        for (k, a) in self.digits.iter().enumerate() {
+            let b = other.digits.get(k).copied().unwrap_or(0);
+            let (d, b1) = a.overflowing_sub(b);
+            let (d, b2) = d.overflowing_sub(borrow as u32);
+            digits.push(d);
+            borrow = b1 || b2;
+        }
+        Some(Self::from_digits(digits))
+    }
+
+    // Divide by a single digit, returning the quotient and remainder
+    fn div_rem_small(&self, divisor: u32) -> (Self, u32) {
+        let mut digits: Vec<u32> = vec![0; self.digits.len()];
+        let mut rem: u64 = 0;
+        for k in (0..self.digits.len()).rev() {
+            let cur = (rem << 32) | self.digits[k] as u64;
+            digits[k] = (cur / divisor as u64) as u32;
+            rem = cur % divisor as u64;
+        }
+        (Self::from_digits(digits), rem as u32)
+    }
+
+    /// Quotient and remainder. Panics if `divisor` is zero.
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        if divisor.is_zero() {
+            panic!("division by zero");
+        }
+        if divisor.digits.len() == 1 {
+            let (q, r) = self.div_rem_small(divisor.digits[0]);
+            return (q, Self::from(r as u64));
+        }
+        if *self < *divisor {
+            return (Self::zero(), self.clone());
+        }
+        // Binary long division
+        let mut quotient: Vec<u32> = vec![0; self.digits.len()];
+        let mut rem = Self::zero();
+        for k in (0..self.bits()).rev() {
+            rem = rem.shl1(self.bit(k));
+            if rem >= *divisor {
+                rem = rem.checked_sub(divisor).unwrap();
+                quotient[k / 32] |= 1 << (k % 32);
+            }
+        }
+        (Self::from_digits(quotient), rem)
+    }
+
+    // Multiply by two and add `low_bit`
+    fn shl1(&self, low_bit: bool) -> Self {
+        let mut digits: Vec<u32> = Vec::with_capacity(self.digits.len() + 1);
+        let mut carry = low_bit as u32;
+        for d in self.digits.iter() {
+            digits.push((d << 1) | carry);
+            carry = d >> 31;
+        }
+        digits.push(carry);
+        Self::from_digits(digits)
+    }
+
+    pub fn pow(&self, exp: u32) -> Self {
+        let mut out_val = Self::one();
+        let mut base = self.clone();
+        let mut exp = exp;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                out_val = &out_val * &base;
+            }
+            base = &base * &base;
+            exp >>= 1;
+        }
+        out_val
+    }
+
+    pub fn gcd(&self, other: &Self) -> Self {
+        let mut u = self.clone();
+        let mut v = other.clone();
+        while !u.is_zero() {
+            let t = u.clone();
+            u = &v % &u;
+            v = t;
+        }
+        v
+    }
+
+    pub fn lcm(&self, other: &Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            Self::zero()
+        } else {
+            &(self / &self.gcd(other)) * other
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(val: u64) -> Self {
+        Self::from_digits(vec![val as u32, (val >> 32) as u32])
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(val: u128) -> Self {
+        Self::from_digits((0..4).map(|k| (val >> (32 * k)) as u32).collect())
+    }
+}
+
+impl From<usize> for BigUint {
+    fn from(val: usize) -> Self {
+        Self::from(val as u64)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: Self) -> BigUint {
+        let len = self.digits.len().max(other.digits.len());
+        let mut digits: Vec<u32> = Vec::with_capacity(len + 1);
+        let mut carry: u64 = 0;
+        for k in 0..len {
+            let a = self.digits.get(k).copied().unwrap_or(0) as u64;
+            let b = other.digits.get(k).copied().unwrap_or(0) as u64;
+            let s = a + b + carry;
+            digits.push(s as u32);
+            carry = s >> 32;
+        }
+        digits.push(carry as u32);
+        BigUint::from_digits(digits)
+    }
+}
+
+impl Sub for &BigUint {
+    type Output = BigUint;
+
+    /// Panics if the result would be negative.
+    fn sub(self, other: Self) -> BigUint {
+        self.checked_sub(other)
+            .expect("attempt to subtract with overflow")
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: Self) -> BigUint {
+        let mut digits: Vec<u32> = vec![0; self.digits.len() + other.digits.len()];
+        for (j, a) in self.digits.iter().enumerate() {
+            let mut carry: u64 = 0;
+            for (k, b) in other.digits.iter().enumerate() {
+                let cur = digits[j + k] as u64 + (*a as u64) * (*b as u64) + carry;
+                digits[j + k] = cur as u32;
+                carry = cur >> 32;
+            }
+            digits[j + other.digits.len()] = carry as u32;
+        }
+        BigUint::from_digits(digits)
+    }
+}
+
+impl Div for &BigUint {
+    type Output = BigUint;
+
+    fn div(self, other: Self) -> BigUint {
+        self.div_rem(other).0
+    }
+}
+
+impl Rem for &BigUint {
+    type Output = BigUint;
+
+    fn rem(self, other: Self) -> BigUint {
+        self.div_rem(other).1
+    }
+}
+
+// Operations on owned values defer to the ones on references
+macro_rules! forward_owned_ops {
+    ($t:ty, $($tr:ident $method:ident),*) => {
+        $(
+            impl $tr for $t {
+                type Output = $t;
+
+                fn $method(self, other: Self) -> $t {
+                    (&self).$method(&other)
+                }
+            }
+        )*
+    };
+}
+
+forward_owned_ops!(BigUint, Add add, Sub sub, Mul mul, Div div, Rem rem);
+
+impl Sum for BigUint {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, k| &acc + &k)
+    }
+}
+
+impl Product for BigUint {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::one(), |acc, k| &acc * &k)
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Peel off nine decimal digits at a time
+        let mut chunks: Vec<u32> = vec![];
+        let mut cur = self.clone();
+        while !cur.is_zero() {
+            let (q, r) = cur.div_rem_small(1_000_000_000);
+            chunks.push(r);
+            cur = q;
+        }
+        let mut out_str = chunks.pop().unwrap().to_string();
+        while let Some(k) = chunks.pop() {
+            out_str.push_str(&format!("{:09}", k));
+        }
+        f.pad_integral(true, "", &out_str)
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+            return Err(Box::new(AdventError(format!("invalid integer: {}", s))));
+        }
+        let ten = Self::from(10_u64);
+        Ok(s.chars().fold(Self::zero(), |acc, c| {
+            &(&acc * &ten) + &Self::from(c.to_digit(10).unwrap() as u64)
+        }))
+    }
+}
+
+/// An arbitrary-precision signed integer.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    // zero is never negative
+    negative: bool,
+    magnitude: BigUint,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    fn new(negative: bool, magnitude: BigUint) -> Self {
+        Self {
+            negative: negative && !magnitude.is_zero(),
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> BigUint {
+        self.magnitude.clone()
+    }
+
+    pub fn signum(&self) -> Ordering {
+        if self.negative {
+            Ordering::Less
+        } else if self.is_zero() {
+            Ordering::Equal
+        } else {
+            Ordering::Greater
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        let m = self.magnitude.to_u64()?;
+        if self.negative {
+            0_i64.checked_sub_unsigned(m)
+        } else {
+            i64::try_from(m).ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        if self.negative {
+            -self.magnitude.to_f64()
+        } else {
+            self.magnitude.to_f64()
+        }
+    }
+
+    pub fn pow(&self, exp: u32) -> Self {
+        Self::new(self.negative && exp % 2 == 1, self.magnitude.pow(exp))
+    }
+
+    /// Greatest common divisor of the absolute values.
+    pub fn gcd(&self, other: &Self) -> BigUint {
+        self.magnitude.gcd(&other.magnitude)
+    }
+}
+
+impl From<BigUint> for BigInt {
+    fn from(magnitude: BigUint) -> Self {
+        Self::new(false, magnitude)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(val: i64) -> Self {
+        Self::new(val < 0, BigUint::from(val.unsigned_abs()))
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(val: i128) -> Self {
+        Self::new(val < 0, BigUint::from(val.unsigned_abs()))
+    }
+}
+
+impl From<u64> for BigInt {
+    fn from(val: u64) -> Self {
+        Self::from(BigUint::from(val))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -&self
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: Self) -> BigInt {
+        if self.negative == other.negative {
+            BigInt::new(self.negative, &self.magnitude + &other.magnitude)
+        } else if self.magnitude >= other.magnitude {
+            BigInt::new(self.negative, &self.magnitude - &other.magnitude)
+        } else {
+            BigInt::new(other.negative, &other.magnitude - &self.magnitude)
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: Self) -> BigInt {
+        self + &(-other)
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: Self) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            &self.magnitude * &other.magnitude,
+        )
+    }
+}
+
+impl Div for &BigInt {
+    type Output = BigInt;
+
+    /// Rounds toward zero, like the primitive integer types.
+    fn div(self, other: Self) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            &self.magnitude / &other.magnitude,
+        )
+    }
+}
+
+impl Rem for &BigInt {
+    type Output = BigInt;
+
+    /// Has the sign of `self`, like the primitive integer types.
+    fn rem(self, other: Self) -> BigInt {
+        BigInt::new(self.negative, &self.magnitude % &other.magnitude)
+    }
+}
+
+forward_owned_ops!(BigInt, Add add, Sub sub, Mul mul, Div div, Rem rem);
+
+impl Sum for BigInt {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, k| &acc + &k)
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad_integral(!self.negative, "", &self.magnitude.to_string())
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('-') {
+            Some(rest) => Ok(Self::new(true, BigUint::from_str(rest)?)),
+            None => Ok(Self::new(false, BigUint::from_str(s)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigUint {
+        BigUint::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn display_round_trip_test() {
+        for s in ["0", "7", "4294967296", "123456789012345678901234567890"] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(BigUint::from(u64::MAX).to_string(), u64::MAX.to_string());
+        assert_eq!(BigInt::from(i64::MIN).to_string(), i64::MIN.to_string());
+        assert_eq!(format!("{:>6}", BigInt::from(-42_i64)), "   -42");
+    }
+
+    #[test]
+    fn arithmetic_test() {
+        let a = BigUint::from(u64::MAX);
+        let b = &a + &BigUint::one();
+        assert_eq!(b.to_string(), "18446744073709551616");
+        assert_eq!(
+            (&a * &a).to_string(),
+            "340282366920938463426481119284349108225"
+        );
+        assert_eq!(&(&a * &a) / &a, a);
+        assert_eq!(&b - &BigUint::one(), a);
+        assert_eq!(b.checked_sub(&(&b + &b)), None);
+        assert_eq!(
+            BigUint::from(2_u64).pow(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+        let x = big("123456789012345678901234567890");
+        let y = big("987654321987654321");
+        let (q, r) = x.div_rem(&y);
+        assert_eq!(&(&q * &y) + &r, x);
+        assert!(r < y);
+    }
+
+    #[test]
+    fn gcd_test() {
+        let p = big("1000000007");
+        let q = big("998244353");
+        let r = big("4294967311");
+        assert_eq!((&p * &r).gcd(&(&q * &r)), r);
+        assert_eq!(p.lcm(&q), &p * &q);
+    }
+
+    #[test]
+    fn signed_test() {
+        let a = BigInt::from(-7_i64);
+        let b = BigInt::from(3_i64);
+        assert_eq!(&a + &b, BigInt::from(-4_i64));
+        assert_eq!(&a - &b, BigInt::from(-10_i64));
+        assert_eq!(&a * &b, BigInt::from(-21_i64));
+        assert_eq!(&a / &b, BigInt::from(-2_i64));
+        assert_eq!(&a % &b, BigInt::from(-1_i64));
+        assert!(a < b);
+        assert_eq!(BigInt::from_str("-0").unwrap(), BigInt::zero());
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+    }
+}
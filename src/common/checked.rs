@@ -0,0 +1,161 @@
+/* src/common/checked.rs
+ *
+ * Arithmetic for the solvers. With the `checked-arith` feature, every operation here is checked,
+ * and an overflow panics with the day, part and operation where it happened (instead of silently
+ * wrapping in a release build). Without the feature, these are just the usual operators.
+ *
+ * Christopher Phan
+ */
+
+use std::cell::Cell;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+/// Integer types the checked operations work on.
+pub trait Arith:
+    Copy + fmt::Display + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_pow(self, exp: u32) -> Option<Self>;
+    fn pow(self, exp: u32) -> Self;
+}
+
+macro_rules! impl_arith {
+    ($($t:ty),*) => {
+        $(
+            impl Arith for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, other)
+                }
+
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+
+                fn checked_pow(self, exp: u32) -> Option<Self> {
+                    <$t>::checked_pow(self, exp)
+                }
+
+                fn pow(self, exp: u32) -> Self {
+                    <$t>::pow(self, exp)
+                }
+            }
+        )*
+    };
+}
+
+impl_arith!(u32, u64, u128, usize, i32, i64, i128, isize);
+
+thread_local! {
+    static SOLVING: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// Restores the previous day and part when dropped.
+pub struct SolvingGuard {
+    previous: Option<(usize, usize)>,
+}
+
+impl Drop for SolvingGuard {
+    fn drop(&mut self) {
+        SOLVING.with(|k| k.set(self.previous));
+    }
+}
+
+/// Attribute overflows to the given day and part until the returned guard is dropped.
+pub fn solving(day: usize, part: usize) -> SolvingGuard {
+    SolvingGuard {
+        previous: SOLVING.with(|k| k.replace(Some((day, part)))),
+    }
+}
+
+fn overflow(op: &str, lhs: impl fmt::Display, symbol: &str, rhs: impl fmt::Display) -> ! {
+    let location = match SOLVING.with(|k| k.get()) {
+        Some((day, part)) => format!("day {}, part {}", day, part),
+        None => "unknown solver".into(),
+    };
+    panic!(
+        "arithmetic overflow in {}: {} ({} {} {})",
+        location, op, lhs, symbol, rhs
+    );
+}
+
+/// `lhs + rhs`; `op` names the operation for overflow reports.
+pub fn add<T: Arith>(lhs: T, rhs: T, op: &str) -> T {
+    if cfg!(feature = "checked-arith") {
+        Arith::checked_add(lhs, rhs).unwrap_or_else(|| overflow(op, lhs, "+", rhs))
+    } else {
+        lhs + rhs
+    }
+}
+
+/// `lhs - rhs`; `op` names the operation for overflow reports.
+pub fn sub<T: Arith>(lhs: T, rhs: T, op: &str) -> T {
+    if cfg!(feature = "checked-arith") {
+        Arith::checked_sub(lhs, rhs).unwrap_or_else(|| overflow(op, lhs, "-", rhs))
+    } else {
+        lhs - rhs
+    }
+}
+
+/// `lhs * rhs`; `op` names the operation for overflow reports.
+pub fn mul<T: Arith>(lhs: T, rhs: T, op: &str) -> T {
+    if cfg!(feature = "checked-arith") {
+        Arith::checked_mul(lhs, rhs).unwrap_or_else(|| overflow(op, lhs, "*", rhs))
+    } else {
+        lhs * rhs
+    }
+}
+
+/// `base` to the power `exp`; `op` names the operation for overflow reports.
+pub fn pow<T: Arith>(base: T, exp: u32, op: &str) -> T {
+    if cfg!(feature = "checked-arith") {
+        Arith::checked_pow(base, exp).unwrap_or_else(|| overflow(op, base, "^", exp))
+    } else {
+        Arith::pow(base, exp)
+    }
+}
+
+pub fn sum<T: Arith, I: IntoIterator<Item = T>>(iter: I, op: &str) -> T {
+    iter.into_iter().fold(T::ZERO, |acc, k| add(acc, k, op))
+}
+
+pub fn product<T: Arith, I: IntoIterator<Item = T>>(iter: I, op: &str) -> T {
+    iter.into_iter().fold(T::ONE, |acc, k| mul(acc, k, op))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_overflow_test() {
+        let _part = solving(0, 1);
+        assert_eq!(add(2_u64, 3, "test"), 5);
+        assert_eq!(sub(2_i64, 3, "test"), -1);
+        assert_eq!(pow(2_u64, 10, "test"), 1024);
+        assert_eq!(sum(vec![1_u64, 2, 3], "test"), 6);
+        assert_eq!(product(vec![1_u64, 2, 3, 4], "test"), 24);
+    }
+
+    #[cfg(feature = "checked-arith")]
+    #[test]
+    #[should_panic(
+        expected = "arithmetic overflow in day 0, part 2: test (9223372036854775807 + 1)"
+    )]
+    fn overflow_report_test() {
+        let _part = solving(0, 2);
+        add(i64::MAX, 1, "test");
+    }
+}
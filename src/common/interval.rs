@@ -0,0 +1,462 @@
+/* src/common/interval.rs
+ *
+ * Sets of integers stored as sorted, disjoint, non-adjacent closed intervals.
+ *
+ * Christopher Phan
+ */
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::RangeInclusive;
+
+/// Integer types that can be used as the elements of an `IntervalSet`.
+pub trait IntervalBound: Copy + Ord + fmt::Debug {
+    const MIN: Self;
+    const MAX: Self;
+
+    fn checked_succ(self) -> Option<Self>;
+    fn checked_pred(self) -> Option<Self>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+
+    /// Number of integers strictly after `self` up to and including `other` (`other >= self`).
+    fn span(self, other: Self) -> u128;
+}
+
+macro_rules! impl_interval_bound {
+    ($($t:ty),*) => {
+        $(
+            impl IntervalBound for $t {
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn checked_succ(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn checked_pred(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, other)
+                }
+
+                fn span(self, other: Self) -> u128 {
+                    // Two's complement wrap-around gives the right answer even for the full range
+                    // of `i128`, since the true difference always fits in a `u128`.
+                    (other as i128).wrapping_sub(self as i128) as u128
+                }
+            }
+        )*
+    };
+}
+
+impl_interval_bound!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// A set of integers, stored as a sorted list of disjoint, non-adjacent closed intervals.
+///
+/// Membership is checked by binary search; set operations are linear in the number of intervals.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T: IntervalBound> {
+    intervals: Vec<(T, T)>,
+}
+
+impl<T: IntervalBound> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self { intervals: vec![] }
+    }
+
+    pub fn single(val: T) -> Self {
+        Self {
+            intervals: vec![(val, val)],
+        }
+    }
+
+    /// The set of all integers in `range` (empty if `range` is empty).
+    pub fn from_range(range: RangeInclusive<T>) -> Self {
+        let (first, last) = range.into_inner();
+        if first <= last {
+            Self {
+                intervals: vec![(first, last)],
+            }
+        } else {
+            Self::new()
+        }
+    }
+
+    /// The union of the given ranges, which may overlap and be in any order.
+    pub fn from_ranges<I: IntoIterator<Item = RangeInclusive<T>>>(ranges: I) -> Self {
+        let mut raw: Vec<(T, T)> = ranges
+            .into_iter()
+            .map(|k| k.into_inner())
+            .filter(|(first, last)| first <= last)
+            .collect();
+        raw.sort();
+        let mut intervals: Vec<(T, T)> = vec![];
+        for val in raw {
+            push_coalesce(&mut intervals, val);
+        }
+        Self { intervals }
+    }
+
+    /// All values of `T` from `T::MIN` to `T::MAX`.
+    pub fn full() -> Self {
+        Self::from_range(T::MIN..=T::MAX)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Number of elements in the set (saturating at `u128::MAX`).
+    pub fn len(&self) -> u128 {
+        self.intervals
+            .iter()
+            .map(|(first, last)| first.span(*last).saturating_add(1))
+            .fold(0, u128::saturating_add)
+    }
+
+    pub fn num_intervals(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.intervals.first().map(|(first, _)| *first)
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.intervals.last().map(|(_, last)| *last)
+    }
+
+    // Index of the first interval whose last element is at least `val`
+    fn search(&self, val: T) -> usize {
+        self.intervals.partition_point(|(_, last)| *last < val)
+    }
+
+    pub fn contains(&self, val: T) -> bool {
+        match self.intervals.get(self.search(val)) {
+            Some((first, _)) => *first <= val,
+            None => false,
+        }
+    }
+
+    /// The maximal interval of the set containing `val`, if any.
+    pub fn interval_containing(&self, val: T) -> Option<RangeInclusive<T>> {
+        match self.intervals.get(self.search(val)) {
+            Some((first, last)) if *first <= val => Some(*first..=*last),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, val: T) {
+        self.insert_range(val..=val);
+    }
+
+    pub fn insert_range(&mut self, range: RangeInclusive<T>) {
+        *self = self.union(&Self::from_range(range));
+    }
+
+    /// The maximal intervals making up the set, in increasing order.
+    pub fn intervals(&self) -> impl Iterator<Item = RangeInclusive<T>> + '_ {
+        self.intervals.iter().map(|(first, last)| *first..=*last)
+    }
+
+    /// The elements of the set, in increasing order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            intervals: self.intervals.iter(),
+            current: None,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut intervals: Vec<(T, T)> = vec![];
+        let mut left = self.intervals.iter().peekable();
+        let mut right = other.intervals.iter().peekable();
+        loop {
+            let next_val = match (left.peek(), right.peek()) {
+                (Some(a), Some(b)) => {
+                    if a.0 <= b.0 {
+                        left.next()
+                    } else {
+                        right.next()
+                    }
+                }
+                (Some(_), None) => left.next(),
+                (None, Some(_)) => right.next(),
+                (None, None) => None,
+            };
+            match next_val {
+                Some(val) => push_coalesce(&mut intervals, *val),
+                None => break,
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals: Vec<(T, T)> = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a_first, a_last) = self.intervals[i];
+            let (b_first, b_last) = other.intervals[j];
+            let first = a_first.max(b_first);
+            let last = a_last.min(b_last);
+            if first <= last {
+                intervals.push((first, last));
+            }
+            if a_last < b_last {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        match (self.min(), self.max()) {
+            (Some(first), Some(last)) => self.intersection(&other.complement(first..=last)),
+            _ => Self::new(),
+        }
+    }
+
+    /// The elements of `within` which are not in the set.
+    pub fn complement(&self, within: RangeInclusive<T>) -> Self {
+        let (lower, upper) = within.into_inner();
+        let mut intervals: Vec<(T, T)> = vec![];
+        if lower > upper {
+            return Self { intervals };
+        }
+        // `next_free` is the smallest value not yet known to be covered; `None` means we have
+        // gone past `T::MAX`.
+        let mut next_free = Some(lower);
+        for (first, last) in self.intervals.iter().copied() {
+            let Some(start) = next_free else {
+                break;
+            };
+            if first > upper {
+                break;
+            }
+            if last < start {
+                continue;
+            }
+            if first > start {
+                intervals.push((start, first.checked_pred().unwrap()));
+            }
+            next_free = last.checked_succ();
+        }
+        if let Some(start) = next_free {
+            if start <= upper {
+                intervals.push((start, upper));
+            }
+        }
+        Self { intervals }
+    }
+
+    /// Add `amount` to every element. Elements that would overflow `T` are dropped.
+    pub fn shift_up(&self, amount: T) -> Self {
+        self.shift_by(|k| k.checked_add(amount))
+    }
+
+    /// Subtract `amount` from every element. Elements that would overflow `T` are dropped.
+    pub fn shift_down(&self, amount: T) -> Self {
+        self.shift_by(|k| k.checked_sub(amount))
+    }
+
+    // `f` must be a monotone translation which is `None` exactly where it overflows
+    fn shift_by<F: Fn(T) -> Option<T>>(&self, f: F) -> Self {
+        let mut intervals: Vec<(T, T)> = vec![];
+        for (first, last) in self.intervals.iter().copied() {
+            match (f(first), f(last)) {
+                (Some(a), Some(b)) => intervals.push((a, b)),
+                (None, Some(b)) => intervals.push((T::MIN, b)),
+                (Some(a), None) => intervals.push((a, T::MAX)),
+                (None, None) => { /* pass */ }
+            }
+        }
+        Self { intervals }
+    }
+
+    /// Split the set into the elements less than `pivot` and those at least `pivot`.
+    pub fn split_at(&self, pivot: T) -> (Self, Self) {
+        let mut below: Vec<(T, T)> = vec![];
+        let mut above: Vec<(T, T)> = vec![];
+        for (first, last) in self.intervals.iter().copied() {
+            if last < pivot {
+                below.push((first, last));
+            } else if first >= pivot {
+                above.push((first, last));
+            } else {
+                below.push((first, pivot.checked_pred().unwrap()));
+                above.push((pivot, last));
+            }
+        }
+        (Self { intervals: below }, Self { intervals: above })
+    }
+}
+
+// Append `val` to a list of intervals sorted by first element, merging it with the last interval
+// if they overlap or are adjacent.
+fn push_coalesce<T: IntervalBound>(intervals: &mut Vec<(T, T)>, val: (T, T)) {
+    if let Some(prev) = intervals.last_mut() {
+        if prev.1.checked_succ().map_or(true, |k| val.0 <= k) {
+            prev.1 = prev.1.max(val.1);
+            return;
+        }
+    }
+    intervals.push(val);
+}
+
+impl<T: IntervalBound> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: IntervalBound> fmt::Debug for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.intervals()).finish()
+    }
+}
+
+impl<T: IntervalBound> From<RangeInclusive<T>> for IntervalSet<T> {
+    fn from(range: RangeInclusive<T>) -> Self {
+        Self::from_range(range)
+    }
+}
+
+impl<T: IntervalBound> FromIterator<T> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_ranges(iter.into_iter().map(|k| k..=k))
+    }
+}
+
+impl<'a, T: IntervalBound> IntoIterator for &'a IntervalSet<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the elements of an `IntervalSet`.
+pub struct Iter<'a, T: IntervalBound> {
+    intervals: std::slice::Iter<'a, (T, T)>,
+    current: Option<(T, T)>,
+}
+
+impl<'a, T: IntervalBound> Iterator for Iter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.current.is_none() {
+            self.current = self.intervals.next().copied();
+        }
+        let (first, last) = self.current?;
+        self.current = if first < last {
+            Some((first.checked_succ().unwrap(), last))
+        } else {
+            None
+        };
+        Some(first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_ranges_test() {
+        let set = IntervalSet::from_ranges(vec![
+            10..=12,
+            1..=3,
+            4..=5,
+            11..=20,
+            RangeInclusive::new(30, 29),
+        ]);
+        assert_eq!(set.intervals().collect::<Vec<_>>(), vec![1..=5, 10..=20]);
+        assert_eq!(set.len(), 16);
+    }
+
+    #[test]
+    fn contains_test() {
+        let set: IntervalSet<u32> = IntervalSet::from_ranges(vec![1..=5, 10..=20, 40..=40]);
+        assert!(set.contains(1));
+        assert!(set.contains(5));
+        assert!(!set.contains(6));
+        assert!(set.contains(15));
+        assert!(!set.contains(39));
+        assert!(set.contains(40));
+        assert!(!set.contains(41));
+        assert!(!set.contains(0));
+        assert_eq!(set.interval_containing(12), Some(10..=20));
+        assert_eq!(set.interval_containing(7), None);
+    }
+
+    #[test]
+    fn set_operations_test() {
+        let a: IntervalSet<i32> = IntervalSet::from_ranges(vec![-5..=5, 10..=20]);
+        let b: IntervalSet<i32> = IntervalSet::from_ranges(vec![0..=12, 18..=25]);
+        assert_eq!(a.union(&b).intervals().collect::<Vec<_>>(), vec![-5..=25]);
+        assert_eq!(
+            a.intersection(&b).intervals().collect::<Vec<_>>(),
+            vec![0..=5, 10..=12, 18..=20]
+        );
+        assert_eq!(
+            a.difference(&b).intervals().collect::<Vec<_>>(),
+            vec![-5..=-1, 13..=17]
+        );
+        assert_eq!(
+            a.complement(-10..=30).intervals().collect::<Vec<_>>(),
+            vec![-10..=-6, 6..=9, 21..=30]
+        );
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+    }
+
+    #[test]
+    fn extreme_values_test() {
+        let set: IntervalSet<u8> = IntervalSet::from_ranges(vec![0..=3, 250..=255]);
+        assert_eq!(
+            set.complement(0..=255).intervals().collect::<Vec<_>>(),
+            vec![4..=249]
+        );
+        assert_eq!(IntervalSet::<u8>::full().len(), 256);
+        assert_eq!(IntervalSet::<i128>::full().len(), u128::MAX);
+        assert!(IntervalSet::<u8>::full().complement(0..=255).is_empty());
+        assert_eq!(
+            set.shift_up(10).intervals().collect::<Vec<_>>(),
+            vec![10..=13]
+        );
+        assert_eq!(
+            set.shift_down(2).intervals().collect::<Vec<_>>(),
+            vec![0..=1, 248..=253]
+        );
+    }
+
+    #[test]
+    fn split_and_iter_test() {
+        let set: IntervalSet<usize> = IntervalSet::from_ranges(vec![1..=3, 7..=9]);
+        let (below, above) = set.split_at(8);
+        assert_eq!(below.iter().collect::<Vec<_>>(), vec![1, 2, 3, 7]);
+        assert_eq!(above.iter().collect::<Vec<_>>(), vec![8, 9]);
+        let from_elements: IntervalSet<usize> = vec![9, 1, 2, 8, 3, 7].into_iter().collect();
+        assert_eq!(from_elements, set);
+    }
+}
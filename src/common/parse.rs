@@ -0,0 +1,280 @@
+/* src/common/parse.rs
+ *
+ * A small parser-combinator toolkit for the puzzle input formats.
+ *
+ * A parser is any function taking the full input and a byte position, and returning the parsed
+ * value along with the position just after it. Plain functions like `uint` and `ws` are parsers
+ * themselves; the other functions here build parsers out of smaller ones.
+ *
+ * Christopher Phan
+ */
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+pub type ParseResult<O> = Result<(O, usize), ParseError>;
+
+pub trait Parser<O>: Fn(&str, usize) -> ParseResult<O> {}
+
+impl<O, F: Fn(&str, usize) -> ParseResult<O>> Parser<O> for F {}
+
+/// Where parsing failed (as a byte offset into the input) and what was expected there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub pos: usize,
+    pub expected: String,
+}
+
+impl ParseError {
+    pub fn new(pos: usize, expected: &str) -> Self {
+        Self {
+            pos,
+            expected: expected.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parse error at position {}: expected {}",
+            self.pos, self.expected
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// Run `parser` on `input`, requiring that nothing but whitespace is left over.
+pub fn parse_all<O>(parser: impl Parser<O>, input: &str) -> Result<O, ParseError> {
+    let (out, pos) = parser(input, 0)?;
+    let (_, pos) = ws(input, pos)?;
+    if pos == input.len() {
+        Ok(out)
+    } else {
+        Err(ParseError::new(pos, "end of input"))
+    }
+}
+
+fn take_chars(input: &str, pos: usize, pred: impl Fn(char) -> bool) -> usize {
+    match input[pos..].find(|c: char| !pred(c)) {
+        Some(k) => pos + k,
+        None => input.len(),
+    }
+}
+
+/// Skip over any whitespace (possibly none).
+pub fn ws(input: &str, pos: usize) -> ParseResult<()> {
+    Ok(((), take_chars(input, pos, char::is_whitespace)))
+}
+
+/// An unsigned decimal integer.
+pub fn uint<T: FromStr>(input: &str, pos: usize) -> ParseResult<T> {
+    let end = take_chars(input, pos, |c| c.is_ascii_digit());
+    match input[pos..end].parse() {
+        Ok(val) if end > pos => Ok((val, end)),
+        _ => Err(ParseError::new(pos, "integer")),
+    }
+}
+
+/// A decimal integer with an optional leading minus sign.
+pub fn int<T: FromStr>(input: &str, pos: usize) -> ParseResult<T> {
+    let digits_start = if input[pos..].starts_with('-') {
+        pos + 1
+    } else {
+        pos
+    };
+    let end = take_chars(input, digits_start, |c| c.is_ascii_digit());
+    match input[pos..end].parse() {
+        Ok(val) if end > digits_start => Ok((val, end)),
+        _ => Err(ParseError::new(pos, "integer")),
+    }
+}
+
+/// One or more alphanumeric characters.
+pub fn word(input: &str, pos: usize) -> ParseResult<String> {
+    take_while1(char::is_alphanumeric, "word")(input, pos)
+}
+
+/// One or more characters satisfying `pred`; `name` describes them in error messages.
+pub fn take_while1(pred: fn(char) -> bool, name: &'static str) -> impl Parser<String> {
+    move |input: &str, pos: usize| {
+        let end = take_chars(input, pos, pred);
+        if end > pos {
+            Ok((input[pos..end].to_string(), end))
+        } else {
+            Err(ParseError::new(pos, name))
+        }
+    }
+}
+
+/// Exactly the string `expected`.
+pub fn literal(expected: &'static str) -> impl Parser<()> {
+    move |input: &str, pos: usize| {
+        if input[pos..].starts_with(expected) {
+            Ok(((), pos + expected.len()))
+        } else {
+            Err(ParseError::new(pos, &format!("\"{}\"", expected)))
+        }
+    }
+}
+
+/// `parser`, after skipping any leading whitespace.
+pub fn token<O>(parser: impl Parser<O>) -> impl Parser<O> {
+    move |input: &str, pos: usize| {
+        let (_, pos) = ws(input, pos)?;
+        parser(input, pos)
+    }
+}
+
+pub fn map<O, P>(parser: impl Parser<O>, f: impl Fn(O) -> P) -> impl Parser<P> {
+    move |input: &str, pos: usize| {
+        let (out, pos) = parser(input, pos)?;
+        Ok((f(out), pos))
+    }
+}
+
+/// Like `map`, but fails (at the start of the parsed text) when `f` returns `None`.
+pub fn try_map<O, P>(
+    parser: impl Parser<O>,
+    f: impl Fn(O) -> Option<P>,
+    expected: &'static str,
+) -> impl Parser<P> {
+    move |input: &str, pos: usize| {
+        let (_, start) = ws(input, pos)?;
+        let (out, new_pos) = parser(input, pos)?;
+        match f(out) {
+            Some(val) => Ok((val, new_pos)),
+            None => Err(ParseError::new(start, expected)),
+        }
+    }
+}
+
+pub fn pair<A, B>(first: impl Parser<A>, second: impl Parser<B>) -> impl Parser<(A, B)> {
+    move |input: &str, pos: usize| {
+        let (a, pos) = first(input, pos)?;
+        let (b, pos) = second(input, pos)?;
+        Ok(((a, b), pos))
+    }
+}
+
+/// `second`, after `first` (whose output is discarded).
+pub fn preceded<A, B>(first: impl Parser<A>, second: impl Parser<B>) -> impl Parser<B> {
+    map(pair(first, second), |(_, b)| b)
+}
+
+/// `first`, followed by `second` (whose output is discarded).
+pub fn terminated<A, B>(first: impl Parser<A>, second: impl Parser<B>) -> impl Parser<A> {
+    map(pair(first, second), |(a, _)| a)
+}
+
+pub fn delimited<A, B, C>(
+    open: impl Parser<A>,
+    inner: impl Parser<B>,
+    close: impl Parser<C>,
+) -> impl Parser<B> {
+    preceded(open, terminated(inner, close))
+}
+
+/// `parser` if it matches, otherwise `None` without consuming anything.
+pub fn opt<O>(parser: impl Parser<O>) -> impl Parser<Option<O>> {
+    move |input: &str, pos: usize| match parser(input, pos) {
+        Ok((out, new_pos)) => Ok((Some(out), new_pos)),
+        Err(_) => Ok((None, pos)),
+    }
+}
+
+/// Zero or more repetitions of `parser`.
+pub fn many<O>(parser: impl Parser<O>) -> impl Parser<Vec<O>> {
+    move |input: &str, pos: usize| {
+        let mut out_vec: Vec<O> = vec![];
+        let mut pos = pos;
+        while let Ok((out, new_pos)) = parser(input, pos) {
+            if new_pos == pos {
+                break;
+            }
+            out_vec.push(out);
+            pos = new_pos;
+        }
+        Ok((out_vec, pos))
+    }
+}
+
+/// One or more `item`s, separated by `sep`. An `item` must follow every `sep`.
+pub fn separated<O, S>(item: impl Parser<O>, sep: impl Parser<S>) -> impl Parser<Vec<O>> {
+    move |input: &str, pos: usize| {
+        let (first, mut pos) = item(input, pos)?;
+        let mut out_vec: Vec<O> = vec![first];
+        while let Ok((_, after_sep)) = sep(input, pos) {
+            let (out, new_pos) = item(input, after_sep)?;
+            out_vec.push(out);
+            pos = new_pos;
+        }
+        Ok((out_vec, pos))
+    }
+}
+
+/// A `key: value` section, e.g. `Time:      7  15   30`.
+pub fn section<O>(key: &'static str, value: impl Parser<O>) -> impl Parser<O> {
+    preceded(pair(token(literal(key)), token(literal(":"))), value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_test() {
+        let parser = section("Time", many(token(uint::<u64>)));
+        assert_eq!(
+            parse_all(&parser, "Time:      7  15   30"),
+            Ok(vec![7, 15, 30])
+        );
+        assert_eq!(
+            parse_all(&parser, "Distance:  9  40  200"),
+            Err(ParseError::new(0, "\"Time\""))
+        );
+        assert_eq!(
+            parse_all(&parser, "Time: 7 x 30"),
+            Err(ParseError::new(8, "end of input"))
+        );
+    }
+
+    #[test]
+    fn separated_test() {
+        let parser = separated(pair(token(int::<i64>), token(word)), token(literal(",")));
+        assert_eq!(
+            parse_all(&parser, " 3 blue, -4 red"),
+            Ok(vec![(3, "blue".into()), (-4, "red".into())])
+        );
+        assert_eq!(
+            parse_all(&parser, "3 blue, red"),
+            Err(ParseError::new(8, "integer"))
+        );
+    }
+
+    #[test]
+    fn delimited_test() {
+        let parser = pair(
+            token(word),
+            preceded(
+                token(literal("=")),
+                delimited(
+                    token(literal("(")),
+                    pair(token(word), preceded(token(literal(",")), token(word))),
+                    token(literal(")")),
+                ),
+            ),
+        );
+        assert_eq!(
+            parse_all(&parser, "11A = (11B, XXX)"),
+            Ok(("11A".into(), ("11B".into(), "XXX".into())))
+        );
+        assert_eq!(
+            parse_all(&parser, "11A = (11B XXX)"),
+            Err(ParseError::new(11, "\",\""))
+        );
+    }
+}
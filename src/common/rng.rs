@@ -0,0 +1,24 @@
+/* src/common/rng.rs
+ *
+ * A seeded pseudo-random number generator, for generating test data.
+ *
+ * Christopher Phan
+ */
+
+/// A linear congruential generator; the same seed always gives the same numbers.
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// The next number, in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
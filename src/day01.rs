@@ -3,9 +3,13 @@
  * Christopher Phan
  */
 
+use std::cmp::Reverse;
 use std::error::Error;
+use std::fmt;
 
 use crate::common;
+use crate::common::aho_corasick::AhoCorasick;
+use crate::common::checked;
 use crate::common::AdventError;
 
 pub fn run() {
@@ -18,89 +22,616 @@ pub fn run() {
 }
 
 pub fn part_1(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
-    Ok(input
-        .iter()
-        .map(get_number_part_1)
-        .filter(|k| k.is_ok())
-        .map(|k| k.unwrap())
-        .sum())
+    let _part = checked::solving(1, 1);
+    Ok(calibration_sum(&input, &DigitVocabulary::numerals()))
 }
 
 pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
-    Ok(input
+    let _part = checked::solving(1, 2);
+    Ok(calibration_sum(&input, &DigitVocabulary::english()))
+}
+
+/// Sum of the calibration values of the lines which have one.
+pub fn calibration_sum(input: &[String], vocab: &DigitVocabulary) -> u64 {
+    let scanner = vocab.scanner();
+    checked::sum(
+        input.iter().flat_map(|k| get_number(k, &scanner)),
+        "sum of calibration values",
+    )
+}
+
+/// The tokens recognized as digits: optionally the numerals `0`-`9`, plus any words (or other
+/// strings) with the values they stand for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigitVocabulary {
+    numerals: bool,
+    unicode_digits: bool,
+    words: Vec<(String, u64)>,
+}
+
+impl DigitVocabulary {
+    /// Just the numerals `0`-`9` (part 1).
+    pub fn numerals() -> Self {
+        Self {
+            numerals: true,
+            unicode_digits: false,
+            words: vec![],
+        }
+    }
+
+    /// The numerals plus "one" through "nine" (part 2).
+    pub fn english() -> Self {
+        Self::from_word_list(
+            &[
+                "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+            ],
+            1,
+        )
+    }
+
+    /// The numerals plus `words`, standing for consecutive values starting at `first_value`, e.g.
+    /// `from_word_list(&["un", "deux", "trois"], 1)`.
+    pub fn from_word_list(words: &[&str], first_value: u64) -> Self {
+        words
+            .iter()
+            .zip(first_value..)
+            .fold(Self::numerals(), |vocab, (word, val)| {
+                vocab.with_word(word, val)
+            })
+    }
+
+    /// Also recognize `word` as standing for `val`.
+    pub fn with_word(mut self, word: &str, val: u64) -> Self {
+        if !word.is_empty() {
+            self.words.retain(|(w, _)| w != word);
+            self.words.push((word.to_string(), val));
+        }
+        self
+    }
+
+    /// Don't recognize the numerals `0`-`9`, only the words.
+    pub fn without_numerals(mut self) -> Self {
+        self.numerals = false;
+        self
+    }
+
+    /// Also recognize the decimal digits of other scripts, e.g. Arabic-Indic `٣` or full-width
+    /// `３` (see `unicode_digit_value`).
+    pub fn with_unicode_digits(mut self) -> Self {
+        self.unicode_digits = true;
+        self
+    }
+
+    // Every recognized token, with its value
+    fn tokens(&self) -> Vec<(String, u64)> {
+        let mut out_vec: Vec<(String, u64)> = vec![];
+        if self.numerals {
+            out_vec.extend((0..10).map(|d| (d.to_string(), d)));
+        }
+        out_vec.extend(self.words.iter().cloned());
+        out_vec
+    }
+
+    /// Build the automaton for finding the tokens of this vocabulary.
+    pub fn scanner(&self) -> DigitScanner {
+        let tokens = self.tokens();
+        let num_numerals = if self.numerals { 10 } else { 0 };
+        DigitScanner {
+            automaton: AhoCorasick::new(tokens.iter().map(|(w, _)| w)),
+            values: tokens.iter().map(|(_, val)| *val).collect(),
+            kinds: (0..tokens.len())
+                .map(|k| {
+                    if k < num_numerals {
+                        TokenKind::Numeral
+                    } else {
+                        TokenKind::Word
+                    }
+                })
+                .collect(),
+            unicode_digits: self.unicode_digits,
+        }
+    }
+}
+
+/// Finds the first and last digits of a line in a single pass. If several tokens start at the
+/// same place, the longest one wins.
+#[derive(Clone, Debug)]
+pub struct DigitScanner {
+    automaton: AhoCorasick,
+    values: Vec<u64>,
+    kinds: Vec<TokenKind>,
+    unicode_digits: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// One of `0`-`9`
+    Numeral,
+    /// A decimal digit from another script
+    UnicodeDigit,
+    /// A word from the vocabulary
+    Word,
+}
+
+// A token found in a line, at bytes start..end
+#[derive(Copy, Clone, Debug)]
+struct Found {
+    start: usize,
+    end: usize,
+    val: u64,
+    kind: TokenKind,
+}
+
+impl DigitScanner {
+    pub fn first_and_last_digits(&self, s: &str) -> Option<(u64, u64)> {
+        self.first_and_last_tokens(s)
+            .map(|(first, last)| (first.val, last.val))
+    }
+
+    fn first_and_last_tokens(&self, s: &str) -> Option<(Found, Found)> {
+        let mut first: Option<Found> = None;
+        let mut last: Option<Found> = None;
+        self.scan(s, |k| {
+            if first.map_or(true, |f| (k.start, f.end) < (f.start, k.end)) {
+                first = Some(k);
+            }
+            if last.map_or(true, |l| (k.start, k.end) > (l.start, l.end)) {
+                last = Some(k);
+            }
+        });
+        first.zip(last)
+    }
+
+    // The longest token starting at each position, in order, possibly overlapping
+    fn all_tokens(&self, s: &str) -> Vec<Found> {
+        let mut out_vec: Vec<Found> = vec![];
+        self.scan(s, |k| out_vec.push(k));
+        out_vec.sort_by_key(|k| (k.start, Reverse(k.end)));
+        out_vec.dedup_by_key(|k| k.start);
+        out_vec
+    }
+
+    fn scan(&self, s: &str, mut record: impl FnMut(Found)) {
+        let mut state = self.automaton.start_state();
+        for (pos, c) in s.char_indices() {
+            let end = pos + c.len_utf8();
+            if self.unicode_digits && !c.is_ascii() {
+                if let Some(d) = unicode_digit_value(c) {
+                    record(Found {
+                        start: pos,
+                        end,
+                        val: d.into(),
+                        kind: TokenKind::UnicodeDigit,
+                    });
+                }
+            }
+            for (k, b) in s.as_bytes()[pos..end].iter().enumerate() {
+                state = self.automaton.next_state(state, *b);
+                for m in self.automaton.matches_ending(state, pos + k + 1) {
+                    record(Found {
+                        start: m.start,
+                        end: m.end,
+                        val: self.values[m.pattern],
+                        kind: self.kinds[m.pattern],
+                    });
+                }
+            }
+        }
+    }
+
+    /// The calibration value of a line under `mode` (whose `output` is ignored), or `None` if
+    /// it has no digits. With `Unit::Numbers`, a value that doesn't fit in a u64 is an error.
+    pub fn line_value(&self, s: &str, mode: &ExtractionMode) -> Result<Option<u64>, AdventError> {
+        if mode.overlap == Overlap::Overlapping && mode.unit == Unit::Digits {
+            return Ok(get_number(s, self).ok());
+        }
+        let mut tokens = self.all_tokens(s);
+        if mode.overlap == Overlap::NonOverlapping {
+            let mut end = 0;
+            tokens.retain(|k| {
+                let keep = k.start >= end;
+                if keep {
+                    end = k.end;
+                }
+                keep
+            });
+        }
+        let (first, last) = match mode.unit {
+            Unit::Digits => match (tokens.first(), tokens.last()) {
+                (Some(f), Some(l)) => ((f.val, 1), (l.val, 1)),
+                _ => return Ok(None),
+            },
+            Unit::Numbers => {
+                let numbers = numbers(&tokens)?;
+                match (numbers.first(), numbers.last()) {
+                    (Some(f), Some(l)) => (*f, *l),
+                    _ => return Ok(None),
+                }
+            }
+        };
+        concat_digits(first.0, last).map(Some).ok_or_else(|| {
+            AdventError(format!("calibration value of {:?} doesn't fit in a u64", s))
+        })
+    }
+}
+
+// The digits of `val` followed by those of `next`, given as (value, number of digits)
+fn concat_digits(val: u64, next: (u64, u32)) -> Option<u64> {
+    val.checked_mul(10_u64.checked_pow(next.1)?)?
+        .checked_add(next.0)
+}
+
+// Group runs of tokens with no gap between them into numbers, as (value, number of digits)
+fn numbers(tokens: &[Found]) -> Result<Vec<(u64, u32)>, AdventError> {
+    let mut out_vec: Vec<(u64, u32)> = vec![];
+    let mut prev_end: Option<usize> = None;
+    for k in tokens {
+        let digits = k.val.checked_ilog10().unwrap_or(0) + 1;
+        match out_vec.last_mut() {
+            Some((val, num_digits)) if prev_end.is_some_and(|e| k.start <= e) => {
+                *val = concat_digits(*val, (k.val, digits))
+                    .ok_or(AdventError::new("number doesn't fit in a u64"))?;
+                *num_digits += digits;
+            }
+            _ => out_vec.push((k.val, digits)),
+        }
+        prev_end = Some(k.end);
+    }
+    Ok(out_vec)
+}
+
+/// Whether a token may start inside the previous one, as "two" does in "eightwo".
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Overlap {
+    #[default]
+    Overlapping,
+    /// Read left to right, taking the longest token at each position and skipping past it
+    NonOverlapping,
+}
+
+/// What the calibration value is made of.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Unit {
+    /// The first digit followed by the last digit
+    #[default]
+    Digits,
+    /// The first number followed by the last number, where a number is a run of digits with
+    /// nothing between them, e.g. "a12b3four5" has 12 and 345
+    Numbers,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Output {
+    #[default]
+    Sum,
+    PerLine,
+}
+
+/// How to read the calibration values. The default is the puzzle's interpretation.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtractionMode {
+    pub overlap: Overlap,
+    pub unit: Unit,
+    pub output: Output,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Extracted {
+    Sum(u64),
+    /// The value of each line, or `None` for lines without any digits
+    PerLine(Vec<Option<u64>>),
+}
+
+impl fmt::Display for Extracted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sum(k) => write!(f, "{}", k),
+            Self::PerLine(v) => {
+                let lines: Vec<String> = v
+                    .iter()
+                    .map(|k| k.map_or("-".to_string(), |val| val.to_string()))
+                    .collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+        }
+    }
+}
+
+pub fn extract(
+    input: &[String],
+    vocab: &DigitVocabulary,
+    mode: &ExtractionMode,
+) -> Result<Extracted, AdventError> {
+    let scanner = vocab.scanner();
+    let values = input
         .iter()
-        .map(get_number_part_2)
-        .filter(|k| k.is_ok())
-        .map(|k| k.unwrap())
-        .sum())
-}
-
-fn check_for_digit_at_start(x: &str, words: bool) -> Result<u64, AdventError> {
-    if x.is_empty() {
-        Err(AdventError("Empty string".into()))
-    } else if let Some(d) = x.chars().next().unwrap().to_digit(10) {
-        // check for digit
-        Ok(d.into())
-    } else if !words {
-        Err(AdventError("No digits".into()))
-    } else if x.starts_with("one") {
-        Ok(1)
-    } else if x.starts_with("two") {
-        Ok(2)
-    } else if x.starts_with("three") {
-        Ok(3)
-    } else if x.starts_with("four") {
-        Ok(4)
-    } else if x.starts_with("five") {
-        Ok(5)
-    } else if x.starts_with("six") {
-        Ok(6)
-    } else if x.starts_with("seven") {
-        Ok(7)
-    } else if x.starts_with("eight") {
-        Ok(8)
-    } else if x.starts_with("nine") {
-        Ok(9)
-    } else {
-        Err(AdventError("No digits".into()))
+        .map(|k| scanner.line_value(k, mode))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(match mode.output {
+        Output::Sum => Extracted::Sum(
+            values
+                .into_iter()
+                .flatten()
+                .try_fold(0_u64, |acc, k| acc.checked_add(k))
+                .ok_or(AdventError::new(
+                    "sum of calibration values doesn't fit in a u64",
+                ))?,
+        ),
+        Output::PerLine => Extracted::PerLine(values),
+    })
+}
+
+/// A token matched in a line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenMatch {
+    pub text: String,
+    pub value: u64,
+    pub kind: TokenKind,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+impl TokenMatch {
+    fn new(s: &str, found: Found) -> Self {
+        let char_start = s[..found.start].chars().count();
+        Self {
+            text: s[found.start..found.end].to_string(),
+            value: found.val,
+            kind: found.kind,
+            byte_start: found.start,
+            byte_end: found.end,
+            char_start,
+            char_end: char_start + s[found.start..found.end].chars().count(),
+        }
     }
 }
 
-fn get_first_digit(s: &str, words: bool) -> Result<u64, AdventError> {
-    for k in 0..s.len() {
-        if let Ok(d) = check_for_digit_at_start(&s[k..], words) {
-            return Ok(d);
+/// Which tokens produced the calibration value of a line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineExplanation {
+    pub first: TokenMatch,
+    pub last: TokenMatch,
+    pub value: u64,
+}
+
+/// The explanation for each line, or `None` for lines without any digits.
+pub fn explain(input: &[String], vocab: &DigitVocabulary) -> Vec<Option<LineExplanation>> {
+    let scanner = vocab.scanner();
+    input.iter().map(|k| explain_line(k, &scanner)).collect()
+}
+
+pub fn explain_line(s: &str, scanner: &DigitScanner) -> Option<LineExplanation> {
+    let (first, last) = scanner.first_and_last_tokens(s)?;
+    Some(LineExplanation {
+        first: TokenMatch::new(s, first),
+        last: TokenMatch::new(s, last),
+        value: checked::add(
+            checked::mul(first.val, 10, "calibration value"),
+            last.val,
+            "calibration value",
+        ),
+    })
+}
+
+const FIRST_COLOR: &str = "\x1b[1;32m";
+const LAST_COLOR: &str = "\x1b[1;36m";
+const BOTH_COLOR: &str = "\x1b[1;35m";
+const RESET_COLOR: &str = "\x1b[0m";
+
+/// The line with the first token in green and the last in cyan (magenta where they overlap), or
+/// without colour, in `[...]` and `{...}`, followed by how the value was obtained.
+pub fn render_explanation(s: &str, explanation: Option<&LineExplanation>, color: bool) -> String {
+    let Some(ex) = explanation else {
+        return format!("{}  => no digits", s);
+    };
+    let out_str = if color {
+        colored_tokens(s, ex)
+    } else {
+        bracketed_tokens(s, ex)
+    };
+    let describe = |t: &TokenMatch| {
+        format!(
+            "{:?} ({:?} at char {}, byte {})",
+            t.text, t.kind, t.char_start, t.byte_start
+        )
+    };
+    format!(
+        "{}  => {} + {} = {}",
+        out_str,
+        describe(&ex.first),
+        describe(&ex.last),
+        ex.value
+    )
+}
+
+fn colored_tokens(s: &str, ex: &LineExplanation) -> String {
+    let mut out_str = String::new();
+    let mut current: Option<&str> = None;
+    for (pos, c) in s.char_indices() {
+        let in_first = (ex.first.byte_start..ex.first.byte_end).contains(&pos);
+        let in_last = (ex.last.byte_start..ex.last.byte_end).contains(&pos);
+        let color = match (in_first, in_last) {
+            (true, true) => Some(BOTH_COLOR),
+            (true, false) => Some(FIRST_COLOR),
+            (false, true) => Some(LAST_COLOR),
+            (false, false) => None,
+        };
+        if color != current {
+            out_str.push_str(color.unwrap_or(RESET_COLOR));
+            current = color;
         }
+        out_str.push(c);
     }
-    Err(AdventError("No digits found".into()))
+    if current.is_some() {
+        out_str.push_str(RESET_COLOR);
+    }
+    out_str
 }
 
-fn get_last_digit(s: &str, words: bool) -> Result<u64, AdventError> {
-    let ell = s.len() - 1;
-    for k in 0..s.len() {
-        if let Ok(d) = check_for_digit_at_start(&s[(ell - k)..], words) {
-            return Ok(d);
+// e.g. "x[eigh{t]wo}": closing brackets go before opening ones at the same place
+fn bracketed_tokens(s: &str, ex: &LineExplanation) -> String {
+    let mut out_str = String::new();
+    let marks = |out_str: &mut String, pos: usize| {
+        if pos == ex.last.byte_end {
+            out_str.push('}');
+        }
+        if pos == ex.first.byte_end {
+            out_str.push(']');
+        }
+        if pos == ex.first.byte_start {
+            out_str.push('[');
         }
+        if pos == ex.last.byte_start {
+            out_str.push('{');
+        }
+    };
+    for (pos, c) in s.char_indices() {
+        marks(&mut out_str, pos);
+        out_str.push(c);
     }
-    Err(AdventError("No digits found".into()))
+    marks(&mut out_str, s.len());
+    out_str
 }
 
-fn get_number(s: &str, words: bool) -> Result<u64, AdventError> {
-    Ok(get_first_digit(s, words)? * 10 + get_last_digit(s, words)?)
+/// Print the explanation of every line of the input, for the part 2 vocabulary.
+pub fn run_explain(color: bool) {
+    let day1_input = common::get_day(1).unwrap();
+    let explanations = explain(&day1_input, &DigitVocabulary::english());
+    for (idx, (line, ex)) in day1_input.iter().zip(explanations.iter()).enumerate() {
+        println!(
+            "{:5}: {}",
+            idx + 1,
+            render_explanation(line, ex.as_ref(), color)
+        );
+    }
 }
 
-fn get_number_part_1(s: &String) -> Result<u64, AdventError> {
-    get_number(s, false)
+// First code point of each run of ten decimal digits (Unicode category Nd) outside ASCII, as of
+// Unicode 17.0
+const UNICODE_DIGIT_ZEROS: [u32; 76] = [
+    0x0660, 0x06F0, 0x07C0, 0x0966, 0x09E6, 0x0A66, 0x0AE6, 0x0B66, 0x0BE6, 0x0C66, 0x0CE6, 0x0D66,
+    0x0DE6, 0x0E50, 0x0ED0, 0x0F20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80, 0x1A90,
+    0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620, 0xA8D0, 0xA900, 0xA9D0, 0xA9F0, 0xAA50, 0xABF0, 0xFF10,
+    0x104A0, 0x10D30, 0x10D40, 0x11066, 0x110F0, 0x11136, 0x111D0, 0x112F0, 0x11450, 0x114D0,
+    0x11650, 0x116C0, 0x116D0, 0x116DA, 0x11730, 0x118E0, 0x11950, 0x11BF0, 0x11C50, 0x11D50,
+    0x11DA0, 0x11DE0, 0x11F50, 0x16130, 0x16A60, 0x16AC0, 0x16B50, 0x16D70, 0x1CCF0, 0x1D7CE,
+    0x1D7D8, 0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140, 0x1E2F0, 0x1E4F0, 0x1E5F1, 0x1E950, 0x1FBF0,
+];
+
+/// Like `char::to_digit(10)`, but for decimal digits from any script in Unicode 17.0, e.g.
+/// Arabic-Indic `٣`, Devanagari `३` or full-width `３`.
+pub fn unicode_digit_value(c: char) -> Option<u32> {
+    if let Some(d) = c.to_digit(10) {
+        return Some(d);
+    }
+    let code = c as u32;
+    UNICODE_DIGIT_ZEROS
+        .iter()
+        .find(|zero| (**zero..(**zero + 10)).contains(&code))
+        .map(|zero| code - zero)
 }
 
-fn get_number_part_2(s: &String) -> Result<u64, AdventError> {
-    get_number(s, true)
+fn get_number(s: &str, scanner: &DigitScanner) -> Result<u64, AdventError> {
+    let (first, last) = scanner
+        .first_and_last_digits(s)
+        .ok_or(AdventError("No digits found".into()))?;
+    Ok(checked::add(
+        checked::mul(first, 10, "calibration value"),
+        last,
+        "calibration value",
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::common;
+    use crate::common::rng::Lcg;
+    use std::time::Instant;
+
+    /* The original implementation, trying every token at every position, kept to check the
+     * scanner against. */
+    fn match_at_start(tokens: &[(String, u64)], x: &str) -> Option<u64> {
+        tokens
+            .iter()
+            .filter(|(w, _)| x.starts_with(w.as_str()))
+            .max_by_key(|(w, _)| w.len())
+            .map(|(_, val)| *val)
+    }
+
+    fn get_number_naive(s: &str, tokens: &[(String, u64)]) -> Option<u64> {
+        let first = s
+            .char_indices()
+            .find_map(|(k, _)| match_at_start(tokens, &s[k..]))?;
+        let last = s
+            .char_indices()
+            .rev()
+            .find_map(|(k, _)| match_at_start(tokens, &s[k..]))?;
+        Some(first * 10 + last)
+    }
+
+    // Pseudo-random lowercase lines, with the occasional digit
+    fn generated_input(lines: usize, line_len: usize) -> Vec<String> {
+        let mut rng = Lcg::new(0x2023_1201);
+        (0..lines)
+            .map(|_| {
+                (0..line_len)
+                    .map(|_| {
+                        let r = rng.below(260);
+                        if r < 10 {
+                            (b'0' + r as u8) as char
+                        } else {
+                            (b'a' + (r % 26) as u8) as char
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scanner_matches_naive_test() {
+        let vocab = DigitVocabulary::english();
+        let scanner = vocab.scanner();
+        let tokens = vocab.tokens();
+        for line in generated_input(200, 60) {
+            assert_eq!(
+                get_number(&line, &scanner).ok(),
+                get_number_naive(&line, &tokens)
+            );
+        }
+    }
+
+    /* Run with `cargo test --release -- --ignored --nocapture scanner_benchmark` */
+    #[test]
+    #[ignore]
+    fn scanner_benchmark() {
+        let vocab = DigitVocabulary::english();
+        let tokens = vocab.tokens();
+        // Many short lines, and a few long lines with a single digit in the middle
+        let long_line = format!("{}7{}", "x".repeat(2_000_000), "q".repeat(2_000_000));
+        for input in [generated_input(40_000, 100), vec![long_line; 4]] {
+            let start = Instant::now();
+            let naive: u64 = input
+                .iter()
+                .flat_map(|k| get_number_naive(k, &tokens))
+                .sum();
+            let naive_time = start.elapsed();
+            let start = Instant::now();
+            let scanned = calibration_sum(&input, &vocab);
+            let scanner_time = start.elapsed();
+            assert_eq!(naive, scanned);
+            println!(
+                "{} lines, {} bytes: naive {:?}, scanner {:?}",
+                input.len(),
+                input.iter().map(|k| k.len()).sum::<usize>(),
+                naive_time,
+                scanner_time
+            );
+        }
+    }
 
     #[test]
     fn part1_test() {
@@ -127,4 +658,200 @@ zoneight234
         );
         assert_eq!(part_2(example_input).unwrap(), 281);
     }
+
+    #[test]
+    fn zero_test() {
+        let vocab = DigitVocabulary::from_word_list(
+            &[
+                "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+            ],
+            0,
+        );
+        assert_eq!(get_number("zero5sixzeroabc", &vocab.scanner()).unwrap(), 0);
+        assert_eq!(get_number("abcsixzero", &vocab.scanner()).unwrap(), 60);
+        assert!(get_number("abcsixzero", &DigitVocabulary::numerals().scanner()).is_err());
+    }
+
+    #[test]
+    fn french_test() {
+        let vocab = DigitVocabulary::from_word_list(
+            &[
+                "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+            ],
+            1,
+        );
+        let example_input = common::split_string(
+            "deux1neuf
+huitroistrois
+abcun2troisxyz
+7pqrstseizesept"
+                .into(),
+        );
+        assert_eq!(calibration_sum(&example_input, &vocab), 29 + 83 + 13 + 77);
+    }
+
+    #[test]
+    fn custom_tokens_test() {
+        let vocab = DigitVocabulary::numerals()
+            .without_numerals()
+            .with_word("I", 1)
+            .with_word("V", 5)
+            .with_word("IV", 4);
+        assert_eq!(get_number("xIVx3xV", &vocab.scanner()).unwrap(), 45);
+        // The first token is the longest one starting first, the last one the one starting last
+        assert_eq!(vocab.scanner().first_and_last_digits("xIV"), Some((4, 5)));
+        assert!(get_number("123", &vocab.scanner()).is_err());
+    }
+
+    #[test]
+    fn multibyte_and_empty_lines_test() {
+        let example_input = common::split_string(
+            "
+
+café7ünï
+ßtwo→eight€
+日本1語2
+"
+            .into(),
+        );
+        assert_eq!(part_1(example_input.clone()).unwrap(), 77 + 12);
+        assert_eq!(part_2(example_input).unwrap(), 77 + 28 + 12);
+    }
+
+    #[test]
+    fn unicode_digits_test() {
+        let vocab = DigitVocabulary::english().with_unicode_digits();
+        assert_eq!(get_number("٣abcone", &vocab.scanner()).unwrap(), 31);
+        assert_eq!(get_number("x５y", &vocab.scanner()).unwrap(), 55);
+        assert_eq!(get_number("seven९", &vocab.scanner()).unwrap(), 79);
+        assert!(get_number("٣abc", &DigitVocabulary::english().scanner()).is_err());
+        assert_eq!(unicode_digit_value('٠'), Some(0));
+        assert_eq!(unicode_digit_value('𝟗'), Some(9));
+        assert_eq!(unicode_digit_value('x'), None);
+        assert_eq!(unicode_digit_value('½'), None);
+        assert_eq!(unicode_digit_value('\u{110F3}'), Some(3));
+        assert_eq!(unicode_digit_value('\u{1E957}'), Some(7));
+        // Kaktovik numerals are numbers, but not decimal digits
+        assert_eq!(unicode_digit_value('\u{1D2C1}'), None);
+        for zero in UNICODE_DIGIT_ZEROS {
+            for k in 0..10 {
+                let c = char::from_u32(zero + k).unwrap();
+                // Older toolchains don't know about the newer digits
+                if char::UNICODE_VERSION >= (17, 0, 0) {
+                    assert!(c.is_numeric(), "{:X}", zero + k);
+                }
+                assert_eq!(unicode_digit_value(c), Some(k));
+            }
+        }
+    }
+
+    #[test]
+    fn explain_test() {
+        let example_input = common::split_string("eightwothree\nxé7\n\nzoneight234".into());
+        let explanations = explain(&example_input, &DigitVocabulary::english());
+        let ex = explanations[0].as_ref().unwrap();
+        assert_eq!(ex.first.text, "eight");
+        assert_eq!(ex.first.kind, TokenKind::Word);
+        assert_eq!((ex.last.byte_start, ex.last.byte_end), (7, 12));
+        assert_eq!(ex.value, 83);
+        let ex = explanations[1].as_ref().unwrap();
+        assert_eq!(ex.first, ex.last);
+        assert_eq!(ex.first.kind, TokenKind::Numeral);
+        assert_eq!((ex.first.byte_start, ex.first.char_start), (3, 2));
+        assert_eq!(ex.value, 77);
+        assert!(explanations[2].is_none());
+        let ex = explanations[3].as_ref().unwrap();
+        assert_eq!((ex.first.text.as_str(), ex.first.char_start), ("one", 1));
+        assert_eq!((ex.last.text.as_str(), ex.last.char_start), ("4", 10));
+    }
+
+    #[test]
+    fn render_explanation_test() {
+        let scanner = DigitVocabulary::english().scanner();
+        let ex = explain_line("xeightwo", &scanner);
+        assert_eq!(
+            render_explanation("xeightwo", ex.as_ref(), true),
+            "x\x1b[1;32meigh\x1b[1;35mt\x1b[1;36mwo\x1b[0m  => \"eight\" (Word at char 1, byte 1) + \"two\" (Word at char 5, byte 5) = 82"
+        );
+        assert_eq!(
+            render_explanation("xeightwo", ex.as_ref(), false),
+            "x[eigh{t]wo}  => \"eight\" (Word at char 1, byte 1) + \"two\" (Word at char 5, byte 5) = 82"
+        );
+        let ex = explain_line("ab7c", &scanner);
+        assert!(render_explanation("ab7c", ex.as_ref(), false).starts_with("ab[{7}]c  =>"));
+        let ex = explain_line("1two", &scanner);
+        assert!(render_explanation("1two", ex.as_ref(), false).starts_with("[1]{two}  =>"));
+        assert_eq!(render_explanation("abc", None, false), "abc  => no digits");
+    }
+
+    #[test]
+    fn extraction_mode_test() {
+        let example_input = common::split_string("eightwo\na12b3four5\nxyz\n7\nsevenine0".into());
+        let vocab = DigitVocabulary::english();
+        let per_line = |overlap, unit| {
+            let mode = ExtractionMode {
+                overlap,
+                unit,
+                output: Output::PerLine,
+            };
+            extract(&example_input, &vocab, &mode).unwrap()
+        };
+        assert_eq!(
+            per_line(Overlap::Overlapping, Unit::Digits),
+            Extracted::PerLine(vec![Some(82), Some(15), None, Some(77), Some(70)])
+        );
+        assert_eq!(
+            per_line(Overlap::NonOverlapping, Unit::Digits),
+            Extracted::PerLine(vec![Some(88), Some(15), None, Some(77), Some(70)])
+        );
+        assert_eq!(
+            per_line(Overlap::Overlapping, Unit::Numbers),
+            Extracted::PerLine(vec![Some(8282), Some(12345), None, Some(77), Some(790790)])
+        );
+        assert_eq!(
+            per_line(Overlap::NonOverlapping, Unit::Numbers),
+            Extracted::PerLine(vec![Some(88), Some(12345), None, Some(77), Some(70)])
+        );
+        assert_eq!(
+            per_line(Overlap::NonOverlapping, Unit::Digits).to_string(),
+            "88\n15\n-\n77\n70"
+        );
+        assert_eq!(
+            extract(&example_input, &vocab, &ExtractionMode::default()).unwrap(),
+            Extracted::Sum(82 + 15 + 77 + 70)
+        );
+    }
+
+    #[test]
+    fn long_numbers_test() {
+        let vocab = DigitVocabulary::english();
+        let mode = ExtractionMode {
+            unit: Unit::Numbers,
+            ..Default::default()
+        };
+        let fits = common::split_string("a1234567890b9".into());
+        assert_eq!(
+            extract(&fits, &vocab, &mode).unwrap(),
+            Extracted::Sum(12345678909)
+        );
+        let too_long = common::split_string("a12345678901234567890123b".into());
+        assert!(extract(&too_long, &vocab, &mode).is_err());
+        let pair_too_long = common::split_string("9999999999x9999999999".into());
+        assert!(extract(&pair_too_long, &vocab, &mode).is_err());
+    }
+
+    #[test]
+    fn default_mode_matches_all_tokens_test() {
+        let input = generated_input(200, 60);
+        let mode = ExtractionMode::default();
+        let scanner = DigitVocabulary::english().scanner();
+        for line in input.iter() {
+            let tokens = scanner.all_tokens(line);
+            let expected = tokens
+                .first()
+                .zip(tokens.last())
+                .map(|(f, l)| f.val * 10 + l.val);
+            assert_eq!(scanner.line_value(line, &mode).unwrap(), expected);
+        }
+    }
 }
@@ -3,34 +3,45 @@
  * Christopher Phan
  */
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
+use std::fmt;
+use std::fs;
 use std::ops::{Add, AddAssign};
 use std::str::FromStr;
 
 use crate::common;
-use crate::common::AdventError;
+use crate::common::checked;
+use crate::common::parse::{
+    literal, map, opt, pair, parse_all, preceded, separated, token, try_map, uint, word, Parser,
+};
 
 pub fn run() {
+    run_with_bag(&BlockCollection::puzzle_bag());
+}
+
+// e.g. `cargo run -- --bag="10 red, 10 green, 10 blue, 3 yellow" 2`
+pub fn run_with_bag(bag: &BlockCollection) {
     let day2_input = common::get_day(2).unwrap();
     println!(
         "{}",
-        common::soln_output(2, 1, part_1(day2_input.clone()).unwrap())
+        common::soln_output(2, 1, part_1_with_bag(day2_input.clone(), bag).unwrap())
     );
     println!("{}", common::soln_output(2, 2, part_2(day2_input).unwrap()));
 }
 
 pub fn part_1(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
-    let bag = BlockCollection {
-        red: 12,
-        green: 13,
-        blue: 14,
-    };
+    part_1_with_bag(input, &BlockCollection::puzzle_bag())
+}
+
+pub fn part_1_with_bag(input: Vec<String>, bag: &BlockCollection) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(2, 1);
     let mut output: u64 = 0;
     for k in input {
         let game = Game::from_str(&k);
         if let Ok(g) = game {
-            if g.compatible_game(&bag) {
-                output += g.id;
+            if g.compatible_game(bag) {
+                output = checked::add(output, g.id, "sum of game ids");
             }
         }
     }
@@ -38,156 +49,464 @@ pub fn part_1(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
 }
 
 pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(2, 2);
     let mut output: u64 = 0;
     for k in input {
         let game = Game::from_str(&k);
         if let Ok(g) = game {
-            output += g.power();
+            output = checked::add(output, g.power(), "sum of powers");
         }
     }
     Ok(output)
 }
 
-#[derive(Copy, Clone, Debug)]
-struct BlockCollection {
-    pub red: u64,
-    pub green: u64,
-    pub blue: u64,
-}
+/// The colours every bag is expected to have, so that a game which never shows one of them has
+/// power 0
+const STANDARD_COLORS: [&str; 3] = ["red", "green", "blue"];
+
+// Number of blocks of each colour, leaving out the colours with none
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockCollection(BTreeMap<String, u64>);
 
 impl BlockCollection {
-    fn compatible_draw(&self, bag: &Self) -> bool {
-        self.red <= bag.red && self.green <= bag.green && self.blue <= bag.blue
-    }
-
-    fn parse_segment(s: &str) -> Result<Self, Box<dyn Error>> {
-        let mut parts = s.trim().split_whitespace().take(2);
-        let number: u64 = parts
-            .next()
-            .ok_or(AdventError("Bad game syntax".into()))?
-            .parse()?;
-        let color_str: &str = parts.next().ok_or(AdventError("Bad game syntax".into()))?;
-        match color_str {
-            "red" => Ok(BlockCollection {
-                red: number,
-                green: 0,
-                blue: 0,
-            }),
-            "green" => Ok(BlockCollection {
-                red: 0,
-                green: number,
-                blue: 0,
-            }),
-            "blue" => Ok(BlockCollection {
-                red: 0,
-                green: 0,
-                blue: number,
-            }),
-            k => Err(Box::new(AdventError(format!("bad color: {}", k)))),
-        }
-    }
-
-    fn power(&self) -> u64 {
-        self.red * self.green * self.blue
-    }
-
-    fn max_by_cmpt(&self, other: &Self) -> Self {
-        Self {
-            red: self.red.max(other.red),
-            green: self.green.max(other.green),
-            blue: self.blue.max(other.blue),
+    /// The bag of part 1: 12 red, 13 green and 14 blue blocks.
+    pub fn puzzle_bag() -> Self {
+        Self::from_iter([("red", 12), ("green", 13), ("blue", 14)])
+    }
+
+    pub fn get(&self, color: &str) -> u64 {
+        self.0.get(color).copied().unwrap_or(0)
+    }
+
+    pub fn colors(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(|k| k.as_str())
+    }
+
+    pub fn total(&self) -> u64 {
+        checked::sum(self.0.values().copied(), "total blocks")
+    }
+
+    pub fn compatible_draw(&self, bag: &Self) -> bool {
+        self.first_excess(bag).is_none()
+    }
+
+    // The first colour (alphabetically) with more blocks than the bag has
+    fn first_excess(&self, bag: &Self) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(color, number)| **number > bag.get(color))
+            .map(|(color, _)| color.as_str())
+    }
+
+    pub fn single_color(number: u64, color: &str) -> Self {
+        Self::from_iter([(color, number)])
+    }
+
+    pub fn power(&self) -> u64 {
+        let colors: BTreeSet<&str> = STANDARD_COLORS.into_iter().chain(self.colors()).collect();
+        checked::product(colors.into_iter().map(|k| self.get(k)), "power")
+    }
+
+    pub fn max_by_cmpt(&self, other: &Self) -> Self {
+        let mut output = self.clone();
+        for (color, number) in other.0.iter() {
+            let entry = output.0.entry(color.clone()).or_insert(0);
+            *entry = (*entry).max(*number);
         }
+        output
     }
 }
 
+impl<S: Into<String>> FromIterator<(S, u64)> for BlockCollection {
+    fn from_iter<I: IntoIterator<Item = (S, u64)>>(iter: I) -> Self {
+        let mut output = Self::default();
+        for (color, number) in iter {
+            if number > 0 {
+                *output.0.entry(color.into()).or_insert(0) += number;
+            }
+        }
+        output
+    }
+}
+
+// e.g. "3 blue"
+fn segment_parser() -> impl Parser<BlockCollection> {
+    map(pair(token(uint::<u64>), token(word)), |(number, color)| {
+        BlockCollection::single_color(number, &color)
+    })
+}
+
+// e.g. "3 blue, 4 red"
+fn draw_parser() -> impl Parser<BlockCollection> {
+    map(
+        separated(segment_parser(), token(literal(","))),
+        |segments| {
+            segments
+                .into_iter()
+                .fold(BlockCollection::default(), |acc, k| acc + k)
+        },
+    )
+}
+
+// A draw in a game, which has to show at least one block (so not e.g. "0 red")
+fn game_draw_parser() -> impl Parser<BlockCollection> {
+    try_map(
+        draw_parser(),
+        |k| if k.0.is_empty() { None } else { Some(k) },
+        "a draw of at least one block",
+    )
+}
+
+// e.g. "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green", or "Game 2:" with no draws
+fn game_parser() -> impl Parser<Game> {
+    map(
+        pair(
+            preceded(token(literal("Game")), token(uint::<u64>)),
+            preceded(
+                token(literal(":")),
+                opt(separated(game_draw_parser(), token(literal(";")))),
+            ),
+        ),
+        |(id, draws)| Game {
+            id,
+            draws: draws.unwrap_or_default(),
+        },
+    )
+}
+
 impl FromStr for BlockCollection {
     type Err = Box<dyn Error>;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut output = Self {
-            red: 0,
-            green: 0,
-            blue: 0,
-        };
-        for p in s.trim().split(',') {
-            output += Self::parse_segment(p)?;
+        Ok(parse_all(draw_parser(), s)?)
+    }
+}
+
+/* Canonical form: colours in alphabetical order, each once, leaving out the ones with no blocks,
+ * and "0 blocks" for an empty collection. This parses back to the same collection as long as
+ * every colour is a single word. */
+impl fmt::Display for BlockCollection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "0 blocks");
         }
-        Ok(output)
+        let segments: Vec<String> = self
+            .0
+            .iter()
+            .map(|(color, number)| format!("{} {}", number, color))
+            .collect();
+        write!(f, "{}", segments.join(", "))
     }
 }
 
 impl Add for BlockCollection {
     type Output = Self;
-    fn add(self, other: Self) -> Self::Output {
-        Self {
-            red: self.red + other.red,
-            green: self.green + other.green,
-            blue: self.blue + other.blue,
-        }
+    fn add(mut self, other: Self) -> Self::Output {
+        self += other;
+        self
     }
 }
 
 impl AddAssign for BlockCollection {
     fn add_assign(&mut self, other: Self) {
-        *self = Self {
-            red: self.red + other.red,
-            green: self.green + other.green,
-            blue: self.blue + other.blue,
-        };
+        for (color, number) in other.0 {
+            *self.0.entry(color).or_insert(0) += number;
+        }
     }
 }
 
-struct Game {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
     pub id: u64,
     pub draws: Vec<BlockCollection>,
 }
 
+/// Why a game can't have been played with a given bag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// Index into `Game::draws`
+    pub draw: usize,
+    pub color: String,
+    pub drawn: u64,
+    pub in_bag: u64,
+}
+
 impl Game {
-    fn compatible_game(&self, bag: &BlockCollection) -> bool {
+    pub fn compatible_game(&self, bag: &BlockCollection) -> bool {
         self.draws.iter().all(|d| d.compatible_draw(bag))
     }
 
-    fn smallest_compat_bag(&self) -> BlockCollection {
-        let mut output = BlockCollection {
-            red: 0,
-            green: 0,
-            blue: 0,
-        };
-        for k in self.draws.iter().cloned() {
-            output = output.max_by_cmpt(&k);
+    /// The first draw with more blocks of some colour than the bag has, or `None` if the game is
+    /// compatible with the bag.
+    pub fn first_violation(&self, bag: &BlockCollection) -> Option<Violation> {
+        self.draws.iter().enumerate().find_map(|(draw, d)| {
+            d.first_excess(bag).map(|color| Violation {
+                draw,
+                color: color.to_string(),
+                drawn: d.get(color),
+                in_bag: bag.get(color),
+            })
+        })
+    }
+
+    pub fn smallest_compat_bag(&self) -> BlockCollection {
+        let mut output = BlockCollection::default();
+        for k in self.draws.iter() {
+            output = output.max_by_cmpt(k);
         }
         output
     }
 
-    fn power(&self) -> u64 {
+    pub fn power(&self) -> u64 {
         self.smallest_compat_bag().power()
     }
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Feasibility {
+    /// Ids of the games compatible with the bag
+    pub feasible: Vec<u64>,
+    pub infeasible: Vec<(u64, Violation)>,
+}
+
+pub fn feasibility(games: &[Game], bag: &BlockCollection) -> Feasibility {
+    let mut output = Feasibility::default();
+    for g in games {
+        match g.first_violation(bag) {
+            None => output.feasible.push(g.id),
+            Some(v) => output.infeasible.push((g.id, v)),
+        }
+    }
+    output
+}
+
+/// The bag with the fewest blocks in total that is compatible with at least `k` of the games, or
+/// `None` if there are fewer than `k` games.
+pub fn smallest_bag_for(games: &[Game], k: usize) -> Option<BlockCollection> {
+    if k > games.len() {
+        return None;
+    }
+    if k == 0 {
+        return Some(BlockCollection::default());
+    }
+    let mins: Vec<BlockCollection> = games.iter().map(|g| g.smallest_compat_bag()).collect();
+    let colors: BTreeSet<&str> = mins.iter().flat_map(|b| b.colors()).collect();
+    let colors: Vec<&str> = colors.into_iter().collect();
+    let mut best: Option<BlockCollection> = None;
+    let candidates: Vec<&BlockCollection> = mins.iter().collect();
+    search_bags(
+        &candidates,
+        &colors,
+        k,
+        BlockCollection::default(),
+        &mut best,
+    );
+    best
+}
+
+/* The bag only needs as many blocks of each colour as some game does, so try each of those
+ * amounts for the colours in turn, keeping the games still compatible. For the last colour, the
+ * k-th smallest amount among the remaining games is the best choice. */
+fn search_bags(
+    candidates: &[&BlockCollection],
+    colors: &[&str],
+    k: usize,
+    chosen: BlockCollection,
+    best: &mut Option<BlockCollection>,
+) {
+    if best.as_ref().is_some_and(|b| b.total() <= chosen.total()) {
+        return;
+    }
+    match colors {
+        [] => *best = Some(chosen),
+        [color] => {
+            let mut amounts: Vec<u64> = candidates.iter().map(|b| b.get(color)).collect();
+            amounts.sort_unstable();
+            search_bags(
+                candidates,
+                &[],
+                k,
+                chosen + BlockCollection::single_color(amounts[k - 1], color),
+                best,
+            );
+        }
+        [color, rest @ ..] => {
+            let amounts: BTreeSet<u64> = candidates.iter().map(|b| b.get(color)).collect();
+            for amount in amounts {
+                let remaining: Vec<&BlockCollection> = candidates
+                    .iter()
+                    .copied()
+                    .filter(|b| b.get(color) <= amount)
+                    .collect();
+                if remaining.len() >= k {
+                    search_bags(
+                        &remaining,
+                        rest,
+                        k,
+                        chosen.clone() + BlockCollection::single_color(amount, color),
+                        best,
+                    );
+                }
+            }
+        }
+    }
+}
+
 impl FromStr for Game {
     type Err = Box<dyn Error>;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts1 = s.trim().split(':');
-        let game_num_part = parts1.next().ok_or(AdventError("Bad game syntax".into()))?;
-        let id: u64 = game_num_part
-            .trim()
-            .split_whitespace()
-            .last()
-            .ok_or(AdventError("Bad game syntax".into()))?
-            .parse()?;
-        let draws_raw: Vec<&str> = parts1
-            .next()
-            .ok_or(AdventError("Bad game syntax".into()))?
-            .trim()
-            .split(';')
-            .collect();
-        let mut draws: Vec<BlockCollection> = vec![];
-        for d in draws_raw {
-            let d_parsed = BlockCollection::from_str(d)?;
-            draws.push(d_parsed);
+        Ok(parse_all(game_parser(), s)?)
+    }
+}
+
+/* This parses back to the same game, unless one of the draws is empty: that is written "0 blocks",
+ * which isn't allowed as a draw. */
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Game {}:", self.id)?;
+        if !self.draws.is_empty() {
+            let draws: Vec<String> = self.draws.iter().map(|k| k.to_string()).collect();
+            write!(f, " {}", draws.join("; "))?;
         }
-        Ok(Game { id, draws })
+        Ok(())
+    }
+}
+
+/// Rewrite a line of day 2 input in canonical form.
+pub fn normalize_line(s: &str) -> Result<String, Box<dyn Error>> {
+    Ok(Game::from_str(s)?.to_string())
+}
+
+/// Print the day 2 input (or the file at `path`) in canonical form, leaving blank lines and lines
+/// that don't parse as they are.
+pub fn run_normalize(path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let input = match path {
+        Some(p) => common::split_string(fs::read_to_string(p)?),
+        None => common::get_day(2)?,
+    };
+    for (idx, line) in input.iter().enumerate() {
+        if line.trim().is_empty() {
+            println!("{}", line);
+        } else {
+            match normalize_line(line) {
+                Ok(k) => println!("{}", k),
+                Err(e) => {
+                    eprintln!("line {}: {}", idx + 1, e);
+                    println!("{}", line);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/* Bag inference: each draw is taken at random, without replacement, from the same bag, and the
+ * blocks are put back before the next draw. A draw of x_c blocks of each colour c (n in all) from
+ * a bag with b_c blocks of each colour (N in all) then has probability
+ *     prod_c C(b_c, x_c) / C(N, n).
+ */
+#[derive(Clone, Debug, Default)]
+pub struct BagInference {
+    games: Vec<Game>,
+}
+
+impl BagInference {
+    pub fn from_game(game: &Game) -> Self {
+        Self {
+            games: vec![game.clone()],
+        }
+    }
+
+    /// Pool the draws of several games, assuming they were all played with the same bag.
+    pub fn from_games(games: &[Game]) -> Self {
+        Self {
+            games: games.to_vec(),
+        }
+    }
+
+    /// The smallest bag that could have produced the draws.
+    pub fn minimum_bag(&self) -> BlockCollection {
+        self.games
+            .iter()
+            .fold(BlockCollection::default(), |acc, g| {
+                acc.max_by_cmpt(&g.smallest_compat_bag())
+            })
+    }
+
+    /// Natural log of the probability of the draws, `-inf` if the bag can't have produced them.
+    pub fn log_likelihood(&self, bag: &BlockCollection) -> f64 {
+        let bag_total = bag.total();
+        self.games
+            .iter()
+            .flat_map(|g| g.draws.iter())
+            .map(|d| {
+                if !d.compatible_draw(bag) || d.total() > bag_total {
+                    f64::NEG_INFINITY
+                } else {
+                    d.0.iter()
+                        .map(|(color, number)| ln_choose(bag.get(color), *number))
+                        .sum::<f64>()
+                        - ln_choose(bag_total, d.total())
+                }
+            })
+            .sum()
+    }
+
+    pub fn likelihood(&self, bag: &BlockCollection) -> f64 {
+        self.log_likelihood(bag).exp()
+    }
+
+    /// The bag making the draws most likely, among those with at most `max_per_color` blocks of
+    /// each colour seen (the likelihood can keep growing with the size of the bag, so some bound
+    /// is needed). Ties go to the bag with fewer blocks. `None` if no bag within the bound could
+    /// have produced the draws.
+    ///
+    /// Every bag in the bound is tried, so this takes time exponential in the number of colours.
+    pub fn max_likelihood_bag(&self, max_per_color: u64) -> Option<BlockCollection> {
+        let min_bag = self.minimum_bag();
+        let colors: Vec<&str> = min_bag.colors().collect();
+        if colors.iter().any(|c| min_bag.get(c) > max_per_color) {
+            return None;
+        }
+        let mut best: Option<(f64, BlockCollection)> = None;
+        let mut bag = min_bag.clone();
+        loop {
+            let ll = self.log_likelihood(&bag);
+            // Allowing for rounding when comparing
+            let better = best.as_ref().map_or(true, |(best_ll, best_bag)| {
+                ll > best_ll + 1e-9 || (ll > best_ll - 1e-9 && bag.total() < best_bag.total())
+            });
+            if better {
+                best = Some((ll, bag.clone()));
+            }
+            // Step to the next bag, like an odometer
+            let mut carried = true;
+            for c in colors.iter() {
+                let entry = bag.0.get_mut(*c).unwrap();
+                if *entry < max_per_color {
+                    *entry += 1;
+                    carried = false;
+                    break;
+                }
+                *entry = min_bag.get(c);
+            }
+            if carried {
+                break;
+            }
+        }
+        best.map(|(_, b)| b)
+    }
+}
+
+fn ln_choose(n: u64, k: u64) -> f64 {
+    if k > n {
+        f64::NEG_INFINITY
+    } else {
+        let k = k.min(n - k);
+        (0..k)
+            .map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln())
+            .sum()
     }
 }
 
@@ -195,6 +514,7 @@ impl FromStr for Game {
 mod tests {
     use super::*;
     use crate::common;
+    use crate::common::rng::Lcg;
 
     #[test]
     fn part1_test() {
@@ -221,4 +541,235 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"
         );
         assert_eq!(part_2(example_input).unwrap(), 2286);
     }
+
+    #[test]
+    fn other_colors_test() {
+        let games: Vec<Game> = [
+            "Game 1: 3 yellow, 4 red; 1 red, 2 green, 6 blue; 2 purple",
+            "Game 2: 1 blue, 2 green, 5 red; 3 green, 4 blue, 1 red, 2 yellow",
+        ]
+        .iter()
+        .map(|k| Game::from_str(k).unwrap())
+        .collect();
+        assert_eq!(
+            games[0].smallest_compat_bag(),
+            BlockCollection::from_str("4 red, 2 green, 6 blue, 3 yellow, 2 purple").unwrap()
+        );
+        assert_eq!(games[0].power(), 4 * 2 * 6 * 3 * 2);
+        assert_eq!(games[1].power(), 5 * 3 * 4 * 2);
+        let bag = BlockCollection::from_str("12 red, 13 green, 14 blue, 2 yellow").unwrap();
+        assert!(!games[0].compatible_game(&bag));
+        assert!(games[1].compatible_game(&bag));
+        // A standard colour that never shows up still counts
+        assert_eq!(
+            Game::from_str("Game 3: 2 red, 3 purple").unwrap().power(),
+            0
+        );
+    }
+
+    #[test]
+    fn block_collection_ops_test() {
+        let a = BlockCollection::from_str("3 blue, 4 red, 1 blue").unwrap();
+        let b = BlockCollection::from_str("2 teal, 0 red, 5 blue").unwrap();
+        assert_eq!(a.get("blue"), 4);
+        assert_eq!(b.colors().collect::<Vec<&str>>(), vec!["blue", "teal"]);
+        assert_eq!(
+            a.clone() + b.clone(),
+            BlockCollection::from_str("9 blue, 4 red, 2 teal").unwrap()
+        );
+        assert_eq!(
+            a.max_by_cmpt(&b),
+            BlockCollection::from_str("5 blue, 4 red, 2 teal").unwrap()
+        );
+        assert!(a.compatible_draw(&a.max_by_cmpt(&b)));
+        assert!(!b.compatible_draw(&a));
+    }
+
+    fn example_games() -> Vec<Game> {
+        common::split_string(
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"
+                .into(),
+        )
+        .iter()
+        .map(|k| Game::from_str(k).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn feasibility_test() {
+        let report = feasibility(&example_games(), &BlockCollection::puzzle_bag());
+        assert_eq!(report.feasible, vec![1, 2, 5]);
+        assert_eq!(
+            report.infeasible,
+            vec![
+                (
+                    3,
+                    Violation {
+                        draw: 0,
+                        color: "red".into(),
+                        drawn: 20,
+                        in_bag: 12
+                    }
+                ),
+                (
+                    4,
+                    Violation {
+                        draw: 2,
+                        color: "blue".into(),
+                        drawn: 15,
+                        in_bag: 14
+                    }
+                )
+            ]
+        );
+        let bag = BlockCollection::from_str("30 red, 30 green, 30 blue").unwrap();
+        assert!(feasibility(&example_games(), &bag).infeasible.is_empty());
+    }
+
+    #[test]
+    fn smallest_bag_for_test() {
+        let games = example_games();
+        // Minimal bags: 4r 2g 6b, 1r 3g 4b, 20r 13g 6b, 14r 3g 15b, 6r 3g 2b
+        assert_eq!(
+            smallest_bag_for(&games, 0),
+            Some(BlockCollection::default())
+        );
+        assert_eq!(
+            smallest_bag_for(&games, 1),
+            Some(BlockCollection::from_str("1 red, 3 green, 4 blue").unwrap())
+        );
+        // Games 1 and 2 or games 2 and 5 both need 13 blocks
+        let bag = smallest_bag_for(&games, 2).unwrap();
+        assert_eq!(bag.total(), 13);
+        assert_eq!(feasibility(&games, &bag).feasible.len(), 2);
+        assert_eq!(
+            smallest_bag_for(&games, 3),
+            Some(BlockCollection::from_str("6 red, 3 green, 6 blue").unwrap())
+        );
+        assert_eq!(
+            smallest_bag_for(&games, 5),
+            Some(BlockCollection::from_str("20 red, 13 green, 15 blue").unwrap())
+        );
+        assert_eq!(smallest_bag_for(&games, 6), None);
+    }
+
+    #[test]
+    fn part1_with_bag_test() {
+        let input: Vec<String> = example_games()
+            .iter()
+            .map(|g| format!("Game {}: 1 red", g.id))
+            .collect();
+        let bag = BlockCollection::from_str("1 red").unwrap();
+        assert_eq!(part_1_with_bag(input.clone(), &bag).unwrap(), 15);
+        assert_eq!(
+            part_1_with_bag(input, &BlockCollection::default()).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn likelihood_test() {
+        let game = Game::from_str("Game 1: 2 red").unwrap();
+        let inference = BagInference::from_game(&game);
+        assert_eq!(
+            inference.minimum_bag(),
+            BlockCollection::single_color(2, "red")
+        );
+        assert!((inference.likelihood(&inference.minimum_bag()) - 1.0).abs() < 1e-12);
+        let bag = BlockCollection::from_str("2 red, 2 blue").unwrap();
+        assert!((inference.likelihood(&bag) - 1.0 / 6.0).abs() < 1e-12);
+        assert_eq!(
+            inference.likelihood(&BlockCollection::single_color(1, "red")),
+            0.0
+        );
+    }
+
+    #[test]
+    fn max_likelihood_bag_test() {
+        let game = Game::from_str("Game 1: 1 red; 1 blue").unwrap();
+        // 1 red, 1 blue and 2 red, 2 blue both give 1/4; the smaller bag wins
+        assert_eq!(
+            BagInference::from_game(&game).max_likelihood_bag(10),
+            Some(BlockCollection::from_str("1 red, 1 blue").unwrap())
+        );
+        let game = Game::from_str("Game 2: 2 red; 1 red, 1 blue").unwrap();
+        let inference = BagInference::from_game(&game);
+        assert_eq!(
+            inference.max_likelihood_bag(10),
+            Some(BlockCollection::from_str("3 red, 1 blue").unwrap())
+        );
+        assert_eq!(
+            inference.max_likelihood_bag(2),
+            Some(inference.minimum_bag())
+        );
+        assert_eq!(inference.max_likelihood_bag(1), None);
+        let pooled = BagInference::from_games(&example_games());
+        assert_eq!(
+            pooled.minimum_bag(),
+            BlockCollection::from_str("20 red, 13 green, 15 blue").unwrap()
+        );
+    }
+
+    // Pseudo-random games with draws of up to four colours, some repeated or with no blocks
+    fn generated_games(count: usize) -> Vec<Game> {
+        let colors = ["red", "green", "blue", "yellow", "purple", "teal"];
+        let mut rng = Lcg::new(0x2023_1202);
+        let mut next = |m: u64| rng.below(m);
+        (0..count)
+            .map(|_| Game {
+                id: next(1000),
+                draws: (0..next(5))
+                    .map(|_| {
+                        (0..next(5))
+                            .map(|_| (colors[next(6) as usize], next(30)))
+                            .collect()
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn display_round_trip_test() {
+        let (mut no_draws, mut empty_draws) = (0, 0);
+        for game in generated_games(500) {
+            if game.draws.is_empty() {
+                no_draws += 1;
+            }
+            if game.draws.iter().any(|k| k.total() == 0) {
+                empty_draws += 1;
+                assert!(Game::from_str(&game.to_string()).is_err());
+            } else {
+                assert_eq!(Game::from_str(&game.to_string()).unwrap(), game);
+            }
+            for draw in game.draws.iter() {
+                assert_eq!(&BlockCollection::from_str(&draw.to_string()).unwrap(), draw);
+            }
+        }
+        assert!(no_draws > 0 && empty_draws > 0);
+        for game in example_games() {
+            assert_eq!(Game::from_str(&game.to_string()).unwrap(), game);
+        }
+    }
+
+    #[test]
+    fn normalize_test() {
+        assert_eq!(
+            normalize_line("Game  7:3 blue,4 red , 1 blue;2 green").unwrap(),
+            "Game 7: 4 blue, 4 red; 2 green"
+        );
+        assert_eq!(normalize_line("Game 8 :").unwrap(), "Game 8:");
+        assert!(normalize_line("Game 7: 3 blue; 0 red").is_err());
+        assert!(normalize_line("Game 7: 3 blue;").is_err());
+        assert!(normalize_line("Game 7: 3 blue; ; 2 red").is_err());
+        assert_eq!(
+            normalize_line("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green").unwrap(),
+            "Game 1: 3 blue, 4 red; 6 blue, 2 green, 1 red; 2 green"
+        );
+        assert!(normalize_line("Game 1: 3 blue, red").is_err());
+    }
 }
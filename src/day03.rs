@@ -4,12 +4,19 @@
  */
 
 use std::error::Error;
+use std::fmt;
+use std::iter;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 use crate::common;
+use crate::common::checked;
 
 pub fn run() {
     let day3_input = common::get_day(3).unwrap();
+    for d in EngineMap::read_map_with_diagnostics(&day3_input).1 {
+        eprintln!("warning: {}", d);
+    }
     println!(
         "{}",
         common::soln_output(3, 1, part_1(day3_input.clone()).unwrap())
@@ -21,12 +28,18 @@ pub fn run() {
 }
 
 pub fn part_1(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(3, 1);
     let engine_map = EngineMap::read_map(&input);
     Ok(engine_map.sum_pn())
 }
 pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    part_2_with_rule(input, &GearRule::default())
+}
+
+pub fn part_2_with_rule(input: Vec<String>, rule: &GearRule) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(3, 2);
     let engine_map = EngineMap::read_map(&input);
-    Ok(engine_map.sum_gr())
+    Ok(engine_map.sum_gr(rule))
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -46,22 +59,120 @@ impl MapNumber {
             num_digits,
         })
     }
+}
 
-    fn adjacent_positions(&self) -> Vec<(i64, i64)> {
-        let mut ret_vec: Vec<(i64, i64)> = (-1..2)
-            .map(|k| (self.start_pos.0 - k, self.start_pos.1 - 1))
-            .collect();
-        for k in (self.start_pos.1)..(self.start_pos.1 + self.num_digits as i64) {
-            ret_vec.push((self.start_pos.0 - 1, k));
-            ret_vec.push((self.start_pos.0 + 1, k));
+/// Which cells count as adjacent to a symbol.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Adjacency {
+    /// The four cells above, below, left and right
+    Orthogonal,
+    /// The eight cells around, diagonals included (the puzzle's rule)
+    Diagonal,
+    /// Every cell at most this many rows and columns away
+    Radius(u32),
+}
+
+impl Adjacency {
+    fn offsets(&self) -> Vec<(i64, i64)> {
+        let radius = match self {
+            Self::Orthogonal => return vec![(-1, 0), (0, -1), (0, 1), (1, 0)],
+            Self::Diagonal => 1,
+            Self::Radius(r) => *r as i64,
+        };
+        (-radius..=radius)
+            .flat_map(|dr| (-radius..=radius).map(move |dc| (dr, dc)))
+            .filter(|k| *k != (0, 0))
+            .collect()
+    }
+}
+
+/// How the numbers around a gear are combined into its ratio.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Combination {
+    Product,
+    Sum,
+    Max,
+}
+
+/// What makes a symbol a gear, and how its ratio is worked out. The default is part 2's rule: a
+/// `*` with exactly two numbers around it (diagonals included), whose ratio is their product.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GearRule {
+    pub symbols: Vec<char>,
+    /// Numbers of adjacent numbers for which the symbol is a gear
+    pub neighbors: RangeInclusive<usize>,
+    pub adjacency: Adjacency,
+    pub combination: Combination,
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        Self {
+            symbols: vec!['*'],
+            neighbors: 2..=2,
+            adjacency: Adjacency::Diagonal,
+            combination: Combination::Product,
         }
-        for k in -1..2 {
-            ret_vec.push((
-                self.start_pos.0 + k,
-                self.start_pos.1 + self.num_digits as i64,
-            ));
+    }
+}
+
+// Which number (as an index into `EngineMap::numbers`) occupies each cell of the bounding box of
+// the numbers
+struct NumberIndex {
+    top_left: (i64, i64),
+    width: usize,
+    height: usize,
+    cells: Vec<Option<u32>>,
+}
+
+impl NumberIndex {
+    fn new(numbers: &[MapNumber]) -> Self {
+        let top = numbers.iter().map(|n| n.start_pos.0).min().unwrap_or(0);
+        let bottom = numbers.iter().map(|n| n.start_pos.0).max().unwrap_or(-1);
+        let left = numbers.iter().map(|n| n.start_pos.1).min().unwrap_or(0);
+        let right = numbers
+            .iter()
+            .map(|n| n.start_pos.1 + n.num_digits as i64 - 1)
+            .max()
+            .unwrap_or(-1);
+        let width = (right - left + 1).max(0) as usize;
+        let height = (bottom - top + 1).max(0) as usize;
+        let mut cells: Vec<Option<u32>> = vec![None; width * height];
+        for (idx, n) in numbers.iter().enumerate() {
+            let row = (n.start_pos.0 - top) as usize;
+            let col = (n.start_pos.1 - left) as usize;
+            for cell in cells[(row * width + col)..(row * width + col + n.num_digits)].iter_mut() {
+                *cell = Some(idx as u32);
+            }
+        }
+        Self {
+            top_left: (top, left),
+            width,
+            height,
+            cells,
         }
-        ret_vec
+    }
+
+    fn number_at(&self, pos: (i64, i64)) -> Option<usize> {
+        let row = usize::try_from(pos.0 - self.top_left.0).ok()?;
+        let col = usize::try_from(pos.1 - self.top_left.1).ok()?;
+        if row < self.height && col < self.width {
+            self.cells[row * self.width + col].map(|k| k as usize)
+        } else {
+            None
+        }
+    }
+
+    // The distinct numbers in the cells adjacent to `pos`
+    fn numbers_around(&self, pos: (i64, i64), adjacency: Adjacency) -> Vec<usize> {
+        let mut out_vec: Vec<usize> = adjacency
+            .offsets()
+            .iter()
+            .flat_map(|(dr, dc)| self.number_at((pos.0 + dr, pos.1 + dc)))
+            .collect();
+        out_vec.sort_unstable();
+        out_vec.dedup();
+        out_vec
     }
 }
 
@@ -72,24 +183,38 @@ struct MapSymbol {
 }
 
 impl MapSymbol {
-    fn adjacent_numbers(&self, numbers: &Vec<MapNumber>) -> Vec<MapNumber> {
-        numbers
-            .iter()
-            .filter(|n| n.adjacent_positions().contains(&self.pos))
-            .cloned()
-            .collect()
+    // Indices of the adjacent numbers
+    fn adjacent_numbers(&self, index: &NumberIndex, adjacency: Adjacency) -> Vec<usize> {
+        index.numbers_around(self.pos, adjacency)
     }
 
-    fn gear_ratio(&self, numbers: &Vec<MapNumber>) -> u64 {
-        if self.symb != '*' {
-            0
+    // The numbers around the symbol, if it's a gear under `rule`
+    fn gear_numbers(&self, rule: &GearRule, index: &NumberIndex) -> Option<Vec<usize>> {
+        if !rule.symbols.contains(&self.symb) {
+            return None;
+        }
+        let adj_num = self.adjacent_numbers(index, rule.adjacency);
+        if rule.neighbors.contains(&adj_num.len()) {
+            Some(adj_num)
         } else {
-            let adj_num = self.adjacent_numbers(numbers);
-            if adj_num.len() == 2 {
-                adj_num[0].val * adj_num[1].val
-            } else {
-                0
-            }
+            None
+        }
+    }
+
+    fn is_gear(&self, rule: &GearRule, index: &NumberIndex) -> bool {
+        self.gear_numbers(rule, index).is_some()
+    }
+
+    // 0 if the symbol isn't a gear under `rule`
+    fn gear_ratio(&self, rule: &GearRule, index: &NumberIndex, numbers: &[MapNumber]) -> u64 {
+        let Some(adj_num) = self.gear_numbers(rule, index) else {
+            return 0;
+        };
+        let values = adj_num.iter().map(|k| numbers[*k].val);
+        match rule.combination {
+            Combination::Product => checked::product(values, "gear_ratio"),
+            Combination::Sum => checked::sum(values, "gear_ratio"),
+            Combination::Max => values.max().unwrap_or(0),
         }
     }
 }
@@ -98,64 +223,527 @@ impl MapSymbol {
 struct EngineMap {
     pub numbers: Vec<MapNumber>,
     pub symbols: Vec<MapSymbol>,
+    // Number of rows, and length of the longest row
+    pub size: (usize, usize),
 }
 
 impl EngineMap {
-    fn symbol_pos(&self) -> Vec<(i64, i64)> {
-        self.symbols.iter().to_owned().map(|k| k.pos).collect()
+    // Whether each number is a part number
+    fn part_flags(&self, index: &NumberIndex) -> Vec<bool> {
+        let mut is_part = vec![false; self.numbers.len()];
+        for s in self.symbols.iter() {
+            for k in s.adjacent_numbers(index, Adjacency::Diagonal) {
+                is_part[k] = true;
+            }
+        }
+        is_part
     }
 
     fn part_numbers(&self) -> Vec<MapNumber> {
-        let symb_pos = self.symbol_pos();
+        let index = NumberIndex::new(&self.numbers);
         self.numbers
             .iter()
-            .cloned()
-            .filter(|n| n.adjacent_positions().iter().any(|k| symb_pos.contains(k)))
+            .zip(self.part_flags(&index))
+            .filter(|(_, p)| *p)
+            .map(|(n, _)| *n)
             .collect()
     }
 
+    /* The schematic with part numbers in green, other numbers in red, gears (under `rule`) in
+     * yellow and other symbols in cyan. Without colour, part numbers are put in [brackets] and
+     * gears in {braces}, which moves things to the right. */
+    fn render(&self, rule: &GearRule, color: bool) -> String {
+        let index = NumberIndex::new(&self.numbers);
+        let (num_rows, num_cols) = self.size;
+        // Each cell's character, style and which number or symbol it belongs to
+        let mut grid: Vec<Vec<(char, Style, Option<usize>)>> =
+            vec![vec![('.', Style::Empty, None); num_cols]; num_rows];
+        let mut place = |pos: (i64, i64), c: char, style: Style, item: usize| {
+            if let Some(cell) = usize::try_from(pos.0)
+                .ok()
+                .zip(usize::try_from(pos.1).ok())
+                .and_then(|(row, col)| grid.get_mut(row)?.get_mut(col))
+            {
+                *cell = (c, style, Some(item));
+            }
+        };
+        for (idx, (n, is_part)) in self.numbers.iter().zip(self.part_flags(&index)).enumerate() {
+            let style = if is_part {
+                Style::PartNumber
+            } else {
+                Style::OtherNumber
+            };
+            let text = format!("{:0width$}", n.val, width = n.num_digits);
+            for (k, c) in text.chars().enumerate() {
+                place((n.start_pos.0, n.start_pos.1 + k as i64), c, style, idx);
+            }
+        }
+        for (idx, s) in self.symbols.iter().enumerate() {
+            let style = if s.is_gear(rule, &index) {
+                Style::Gear
+            } else {
+                Style::Symbol
+            };
+            place(s.pos, s.symb, style, self.numbers.len() + idx);
+        }
+        let rows: Vec<String> = grid
+            .iter()
+            .map(|row| {
+                let mut out_str = String::new();
+                for (k, (c, style, item)) in row.iter().enumerate() {
+                    let starts = k == 0 || row[k - 1].2 != *item;
+                    let ends = k + 1 == row.len() || row[k + 1].2 != *item;
+                    if starts {
+                        out_str.push_str(style.open(color));
+                    }
+                    out_str.push(*c);
+                    if ends {
+                        out_str.push_str(style.close(color));
+                    }
+                }
+                out_str
+            })
+            .collect();
+        rows.join("\n")
+    }
+
     fn sum_pn(&self) -> u64 {
-        self.part_numbers().iter().map(|n| n.val).sum()
+        checked::sum(
+            self.part_numbers().iter().map(|n| n.val),
+            "sum of part numbers",
+        )
     }
 
-    fn sum_gr(&self) -> u64 {
-        self.symbols
-            .iter()
-            .map(|k| k.gear_ratio(&self.numbers))
-            .sum()
+    fn sum_gr(&self, rule: &GearRule) -> u64 {
+        let index = NumberIndex::new(&self.numbers);
+        checked::sum(
+            self.symbols
+                .iter()
+                .map(|k| k.gear_ratio(rule, &index, &self.numbers)),
+            "sum of gear ratios",
+        )
+    }
+
+    fn read_map(input: &[String]) -> Self {
+        Self::read_map_with_diagnostics(input).0
     }
 
-    fn read_map(input: &Vec<String>) -> Self {
-        let mut current_num_start: Option<(i64, i64)> = None;
-        let mut num_buffer: String = "".into();
+    /* Each row is read on its own, so a number can't run on from the end of one row to the next.
+     * Trailing empty lines are ignored. */
+    fn read_map_with_diagnostics(input: &[String]) -> (Self, Vec<Diagnostic>) {
         let mut numbers: Vec<MapNumber> = vec![];
         let mut symbols: Vec<MapSymbol> = vec![];
-        for (row, row_str) in input.iter().enumerate() {
-            for (col, c) in row_str.chars().enumerate() {
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+        let num_rows = input
+            .iter()
+            .rposition(|k| !k.is_empty())
+            .map_or(0, |k| k + 1);
+        let width = input.first().map_or(0, |k| k.chars().count());
+        for (row, row_str) in input[..num_rows].iter().enumerate() {
+            let len = row_str.chars().count();
+            if len != width {
+                diagnostics.push(Diagnostic::RaggedRow {
+                    row,
+                    len,
+                    expected: width,
+                });
+            }
+            let mut num_buffer: String = "".into();
+            // An extra '.' at the end of the row to finish off any number there
+            for (col, c) in row_str.chars().chain(iter::once('.')).enumerate() {
                 if c.is_ascii_digit() {
-                    if current_num_start.is_none() {
-                        current_num_start = Some((row as i64, col as i64));
-                    }
                     num_buffer.push(c);
-                } else {
-                    if !num_buffer.is_empty() {
-                        numbers.push(
-                            MapNumber::record(current_num_start.unwrap(), &(num_buffer.clone()))
-                                .unwrap(),
-                        );
-                        current_num_start = None;
-                        num_buffer = "".into();
+                    continue;
+                }
+                if !num_buffer.is_empty() {
+                    let start_col = col - num_buffer.len();
+                    match MapNumber::record((row as i64, start_col as i64), &num_buffer) {
+                        Ok(n) => numbers.push(n),
+                        Err(_) => diagnostics.push(Diagnostic::NumberTooLarge {
+                            row,
+                            col: start_col,
+                            text: num_buffer.clone(),
+                        }),
                     }
-                    if c != '.' {
-                        symbols.push(MapSymbol {
-                            pos: (row as i64, col as i64),
-                            symb: c,
-                        });
+                    num_buffer.clear();
+                }
+                if c != '.' {
+                    if !c.is_ascii_punctuation() {
+                        diagnostics.push(Diagnostic::UnexpectedChar { row, col, c });
                     }
+                    symbols.push(MapSymbol {
+                        pos: (row as i64, col as i64),
+                        symb: c,
+                    });
                 }
             }
         }
-        Self { numbers, symbols }
+        let size = (
+            num_rows,
+            input[..num_rows]
+                .iter()
+                .map(|k| k.chars().count())
+                .max()
+                .unwrap_or(0),
+        );
+        (
+            Self {
+                numbers,
+                symbols,
+                size,
+            },
+            diagnostics,
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Style {
+    PartNumber,
+    OtherNumber,
+    Gear,
+    Symbol,
+    Empty,
+}
+
+impl Style {
+    fn open(&self, color: bool) -> &'static str {
+        match (self, color) {
+            (Self::PartNumber, true) => "\x1b[1;32m",
+            (Self::OtherNumber, true) => "\x1b[31m",
+            (Self::Gear, true) => "\x1b[1;33m",
+            (Self::Symbol, true) => "\x1b[36m",
+            (Self::PartNumber, false) => "[",
+            (Self::Gear, false) => "{",
+            _ => "",
+        }
+    }
+
+    fn close(&self, color: bool) -> &'static str {
+        match (self, color) {
+            (Self::Empty, true) => "",
+            (_, true) => "\x1b[0m",
+            (Self::PartNumber, false) => "]",
+            (Self::Gear, false) => "}",
+            _ => "",
+        }
+    }
+}
+
+/// Print the schematic, showing which numbers are part numbers and which symbols are gears.
+pub fn run_explain(color: bool) {
+    let day3_input = common::get_day(3).unwrap();
+    let (map, diagnostics) = EngineMap::read_map_with_diagnostics(&day3_input);
+    for d in diagnostics {
+        eprintln!("warning: {}", d);
+    }
+    println!("{}", map.render(&GearRule::default(), color));
+    println!();
+    println!("{}", cluster_report(&day3_input));
+    println!();
+    if color {
+        println!(
+            "{}part number{} {}other number{} {}gear{} {}symbol{}",
+            Style::PartNumber.open(true),
+            Style::PartNumber.close(true),
+            Style::OtherNumber.open(true),
+            Style::OtherNumber.close(true),
+            Style::Gear.open(true),
+            Style::Gear.close(true),
+            Style::Symbol.open(true),
+            Style::Symbol.close(true),
+        );
+    } else {
+        println!("[part number] other number {{gear}} symbol");
+    }
+}
+
+/* The bipartite graph with an edge between each number and each symbol adjacent to it (numbers
+ * and symbols are referred to by their indices in the `EngineMap`) */
+struct PartGraph {
+    // The symbols around each number
+    number_adj: Vec<Vec<usize>>,
+    // The numbers around each symbol
+    symbol_adj: Vec<Vec<usize>>,
+}
+
+// A connected component of the graph with at least one edge
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Cluster {
+    numbers: Vec<usize>,
+    symbols: Vec<usize>,
+}
+
+impl PartGraph {
+    fn new(map: &EngineMap) -> Self {
+        let index = NumberIndex::new(&map.numbers);
+        let mut number_adj: Vec<Vec<usize>> = vec![vec![]; map.numbers.len()];
+        let symbol_adj: Vec<Vec<usize>> = map
+            .symbols
+            .iter()
+            .map(|s| s.adjacent_numbers(&index, Adjacency::Diagonal))
+            .collect();
+        for (s, adj) in symbol_adj.iter().enumerate() {
+            for n in adj {
+                number_adj[*n].push(s);
+            }
+        }
+        Self {
+            number_adj,
+            symbol_adj,
+        }
+    }
+
+    // In order of their first number
+    fn clusters(&self) -> Vec<Cluster> {
+        let mut seen = vec![false; self.number_adj.len()];
+        let mut symbol_seen = vec![false; self.symbol_adj.len()];
+        let mut out_vec: Vec<Cluster> = vec![];
+        for start in 0..self.number_adj.len() {
+            if seen[start] || self.number_adj[start].is_empty() {
+                continue;
+            }
+            let mut cluster = Cluster {
+                numbers: vec![],
+                symbols: vec![],
+            };
+            seen[start] = true;
+            let mut stack = vec![start];
+            while let Some(n) = stack.pop() {
+                cluster.numbers.push(n);
+                for s in self.number_adj[n].iter() {
+                    if !symbol_seen[*s] {
+                        symbol_seen[*s] = true;
+                        cluster.symbols.push(*s);
+                        for m in self.symbol_adj[*s].iter() {
+                            if !seen[*m] {
+                                seen[*m] = true;
+                                stack.push(*m);
+                            }
+                        }
+                    }
+                }
+            }
+            cluster.numbers.sort_unstable();
+            cluster.symbols.sort_unstable();
+            out_vec.push(cluster);
+        }
+        out_vec
+    }
+
+    // The cluster with the most numbers (the first one, if there's a tie)
+    fn largest_cluster(clusters: &[Cluster]) -> Option<&Cluster> {
+        clusters
+            .iter()
+            .rev()
+            .max_by_key(|c| (c.numbers.len(), c.symbols.len()))
+    }
+
+    fn numbers_with_several_symbols(&self) -> Vec<usize> {
+        (0..self.number_adj.len())
+            .filter(|k| self.number_adj[*k].len() > 1)
+            .collect()
+    }
+
+    fn lonely_symbols(&self) -> Vec<usize> {
+        (0..self.symbol_adj.len())
+            .filter(|k| self.symbol_adj[*k].is_empty())
+            .collect()
+    }
+
+    fn report(&self, map: &EngineMap) -> String {
+        let describe_numbers = |v: &[usize]| {
+            let vals: Vec<String> = v.iter().map(|k| map.numbers[*k].val.to_string()).collect();
+            if vals.is_empty() {
+                "none".to_string()
+            } else {
+                vals.join(", ")
+            }
+        };
+        let describe_symbols = |v: &[usize]| {
+            let symbs: Vec<String> = v
+                .iter()
+                .map(|k| {
+                    let s = map.symbols[*k];
+                    format!("{} at {:?}", s.symb, s.pos)
+                })
+                .collect();
+            if symbs.is_empty() {
+                "none".to_string()
+            } else {
+                symbs.join(", ")
+            }
+        };
+        let clusters = self.clusters();
+        let num_parts = self.number_adj.iter().filter(|k| !k.is_empty()).count();
+        let mut lines: Vec<String> = vec![
+            format!(
+                "{} ({}), {}, {}",
+                count_of(map.numbers.len(), "number"),
+                count_of(num_parts, "part number"),
+                count_of(map.symbols.len(), "symbol"),
+                count_of(clusters.len(), "cluster")
+            ),
+            format!(
+                "Clusters sharing a symbol: {}",
+                clusters.iter().filter(|c| c.numbers.len() > 1).count()
+            ),
+        ];
+        if let Some(c) = Self::largest_cluster(&clusters) {
+            lines.push(format!(
+                "Largest cluster: numbers {}; symbols {}",
+                describe_numbers(&c.numbers),
+                describe_symbols(&c.symbols)
+            ));
+        }
+        lines.push(format!(
+            "Numbers next to several symbols: {}",
+            describe_numbers(&self.numbers_with_several_symbols())
+        ));
+        lines.push(format!(
+            "Symbols with no numbers: {}",
+            describe_symbols(&self.lonely_symbols())
+        ));
+        lines.join("\n")
+    }
+
+    fn to_json(&self, map: &EngineMap) -> String {
+        let list = |v: &[usize]| {
+            let items: Vec<String> = v.iter().map(|k| k.to_string()).collect();
+            format!("[{}]", items.join(","))
+        };
+        let numbers: Vec<String> = map
+            .numbers
+            .iter()
+            .enumerate()
+            .map(|(k, n)| {
+                format!(
+                    "{{\"id\":{},\"value\":{},\"row\":{},\"col\":{},\"symbols\":{}}}",
+                    k,
+                    n.val,
+                    n.start_pos.0,
+                    n.start_pos.1,
+                    list(&self.number_adj[k])
+                )
+            })
+            .collect();
+        let symbols: Vec<String> = map
+            .symbols
+            .iter()
+            .enumerate()
+            .map(|(k, s)| {
+                format!(
+                    "{{\"id\":{},\"symbol\":{},\"row\":{},\"col\":{},\"numbers\":{}}}",
+                    k,
+                    json_string(&s.symb.to_string()),
+                    s.pos.0,
+                    s.pos.1,
+                    list(&self.symbol_adj[k])
+                )
+            })
+            .collect();
+        let clusters: Vec<String> = self
+            .clusters()
+            .iter()
+            .map(|c| {
+                format!(
+                    "{{\"numbers\":{},\"symbols\":{}}}",
+                    list(&c.numbers),
+                    list(&c.symbols)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"numbers\":[{}],\"symbols\":[{}],\"clusters\":[{}]}}",
+            numbers.join(","),
+            symbols.join(","),
+            clusters.join(",")
+        )
+    }
+}
+
+// e.g. "1 cluster", "3 clusters"
+fn count_of(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out_str = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out_str.push_str("\\\""),
+            '\\' => out_str.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out_str.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out_str.push(c),
+        }
+    }
+    out_str.push('"');
+    out_str
+}
+
+/// Print the graph of numbers and the symbols around them as JSON.
+pub fn run_json() {
+    println!("{}", cluster_json(&common::get_day(3).unwrap()));
+}
+
+pub fn cluster_report(input: &[String]) -> String {
+    let map = EngineMap::read_map(input);
+    PartGraph::new(&map).report(&map)
+}
+
+/* {"numbers": [{"id", "value", "row", "col", "symbols": [symbol ids]}, ...],
+ *  "symbols": [{"id", "symbol", "row", "col", "numbers": [number ids]}, ...],
+ *  "clusters": [{"numbers": [...], "symbols": [...]}, ...]} */
+pub fn cluster_json(input: &[String]) -> String {
+    let map = EngineMap::read_map(input);
+    PartGraph::new(&map).to_json(&map)
+}
+
+// Problems found while reading a schematic (rows and columns counted from 0)
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Diagnostic {
+    RaggedRow {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    // Anything other than a digit, '.' or ASCII punctuation, still read as a symbol
+    UnexpectedChar {
+        row: usize,
+        col: usize,
+        c: char,
+    },
+    // Skipped
+    NumberTooLarge {
+        row: usize,
+        col: usize,
+        text: String,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RaggedRow { row, len, expected } => write!(
+                f,
+                "row {} has {} characters, expected {}",
+                row, len, expected
+            ),
+            Self::UnexpectedChar { row, col, c } => write!(
+                f,
+                "unexpected character {:?} at row {}, column {}",
+                c, row, col
+            ),
+            Self::NumberTooLarge { row, col, text } => write!(
+                f,
+                "number {} at row {}, column {} is too large",
+                text, row, col
+            ),
+        }
     }
 }
 
@@ -163,6 +751,115 @@ impl EngineMap {
 mod tests {
     use super::*;
     use crate::common;
+    use crate::common::rng::Lcg;
+    use std::time::Instant;
+
+    /* The original implementation, checking every number against every symbol, kept to check the
+     * index against. */
+    fn adjacent_positions(n: &MapNumber) -> Vec<(i64, i64)> {
+        let mut ret_vec: Vec<(i64, i64)> = (-1..2)
+            .map(|k| (n.start_pos.0 - k, n.start_pos.1 - 1))
+            .collect();
+        for k in (n.start_pos.1)..(n.start_pos.1 + n.num_digits as i64) {
+            ret_vec.push((n.start_pos.0 - 1, k));
+            ret_vec.push((n.start_pos.0 + 1, k));
+        }
+        for k in -1..2 {
+            ret_vec.push((n.start_pos.0 + k, n.start_pos.1 + n.num_digits as i64));
+        }
+        ret_vec
+    }
+
+    fn sums_naive(map: &EngineMap) -> (u64, u64) {
+        let symb_pos: Vec<(i64, i64)> = map.symbols.iter().map(|k| k.pos).collect();
+        let sum_pn = map
+            .numbers
+            .iter()
+            .filter(|n| adjacent_positions(n).iter().any(|k| symb_pos.contains(k)))
+            .map(|n| n.val)
+            .sum();
+        let sum_gr = map
+            .symbols
+            .iter()
+            .filter(|s| s.symb == '*')
+            .map(|s| {
+                let adj: Vec<u64> = map
+                    .numbers
+                    .iter()
+                    .filter(|n| adjacent_positions(n).contains(&s.pos))
+                    .map(|n| n.val)
+                    .collect();
+                if adj.len() == 2 {
+                    adj[0] * adj[1]
+                } else {
+                    0
+                }
+            })
+            .sum();
+        (sum_pn, sum_gr)
+    }
+
+    // Pseudo-random schematic, with numbers of at most three digits
+    fn generated_schematic(rows: usize, cols: usize) -> Vec<String> {
+        let symbols = ['*', '#', '+', '$', '/', '@', '=', '%', '-', '&', '*', '*'];
+        let mut rng = Lcg::new(0x2023_1203);
+        (0..rows)
+            .map(|_| {
+                let mut digits = 0;
+                (0..cols)
+                    .map(|_| {
+                        let r = rng.below(100);
+                        let c = if r < 55 || digits == 3 {
+                            '.'
+                        } else if r < 90 {
+                            (b'0' + (r % 10) as u8) as char
+                        } else {
+                            symbols[(r % 12) as usize]
+                        };
+                        digits = if c.is_ascii_digit() { digits + 1 } else { 0 };
+                        c
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn index_matches_naive_test() {
+        let map = EngineMap::read_map(&generated_schematic(80, 80));
+        assert_eq!(
+            (map.sum_pn(), map.sum_gr(&GearRule::default())),
+            sums_naive(&map)
+        );
+    }
+
+    /* Run with `cargo test --release -- --ignored --nocapture index_benchmark` */
+    #[test]
+    #[ignore]
+    fn index_benchmark() {
+        let input = generated_schematic(2000, 2000);
+        let start = Instant::now();
+        let map = EngineMap::read_map(&input);
+        println!("read_map: {:?}", start.elapsed());
+        let start = Instant::now();
+        let sums = (map.sum_pn(), map.sum_gr(&GearRule::default()));
+        println!(
+            "indexed ({} numbers, {} symbols): {:?}",
+            map.numbers.len(),
+            map.symbols.len(),
+            start.elapsed()
+        );
+        // The naive version is far too slow for the whole schematic
+        let small_map = EngineMap::read_map(&input[..20]);
+        let start = Instant::now();
+        let small_sums = sums_naive(&small_map);
+        println!("naive, first 20 rows only: {:?}", start.elapsed());
+        assert_eq!(
+            (small_map.sum_pn(), small_map.sum_gr(&GearRule::default())),
+            small_sums
+        );
+        assert!(sums.0 > 0);
+    }
 
     #[test]
     fn part2_test() {
@@ -199,4 +896,209 @@ mod tests {
         );
         assert_eq!(part_1(example_input).unwrap(), 4361);
     }
+
+    #[test]
+    fn row_boundary_test() {
+        let example_input = common::split_string("..12\n34*.\n....\n...5\n".into());
+        let (map, diagnostics) = EngineMap::read_map_with_diagnostics(&example_input);
+        let numbers: Vec<(u64, (i64, i64))> =
+            map.numbers.iter().map(|n| (n.val, n.start_pos)).collect();
+        assert_eq!(numbers, vec![(12, (0, 2)), (34, (1, 0)), (5, (3, 3))]);
+        assert!(diagnostics.is_empty());
+        assert_eq!(part_1(example_input).unwrap(), 12 + 34);
+    }
+
+    #[test]
+    fn diagnostics_test() {
+        let example_input = common::split_string(
+            "467..114..\n...*.\n..35a.633.\n99999999999999999999*.....".into(),
+        );
+        let (map, diagnostics) = EngineMap::read_map_with_diagnostics(&example_input);
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::RaggedRow {
+                    row: 1,
+                    len: 5,
+                    expected: 10
+                },
+                Diagnostic::UnexpectedChar {
+                    row: 2,
+                    col: 4,
+                    c: 'a'
+                },
+                Diagnostic::RaggedRow {
+                    row: 3,
+                    len: 26,
+                    expected: 10
+                },
+                Diagnostic::NumberTooLarge {
+                    row: 3,
+                    col: 0,
+                    text: "99999999999999999999".into()
+                },
+            ]
+        );
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "unexpected character 'a' at row 2, column 4"
+        );
+        assert_eq!(map.numbers.len(), 4);
+        assert_eq!(map.symbols.len(), 3);
+    }
+
+    #[test]
+    fn gear_rule_test() {
+        let example_input = common::split_string(
+            "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598.."
+                .into(),
+        );
+        let rule = |f: fn(&mut GearRule)| {
+            let mut rule = GearRule::default();
+            f(&mut rule);
+            part_2_with_rule(example_input.clone(), &rule).unwrap()
+        };
+        assert_eq!(rule(|_| {}), 467835);
+        assert_eq!(
+            rule(|r| r.combination = Combination::Sum),
+            467 + 35 + 755 + 598
+        );
+        assert_eq!(rule(|r| r.combination = Combination::Max), 467 + 755);
+        // The lone 617 next to the second '*' counts too
+        assert_eq!(
+            rule(|r| {
+                r.neighbors = 1..=2;
+                r.combination = Combination::Sum
+            }),
+            467 + 35 + 617 + 755 + 598
+        );
+        assert_eq!(rule(|r| r.symbols = vec!['#', '+', '$']), 0);
+        assert_eq!(
+            rule(|r| {
+                r.symbols = vec!['#', '+', '$'];
+                r.neighbors = 1..=1
+            }),
+            633 + 592 + 664
+        );
+        // 35 is directly below a '*', 617 directly left of one and 598 directly below one
+        assert_eq!(
+            rule(|r| {
+                r.adjacency = Adjacency::Orthogonal;
+                r.neighbors = 1..=1
+            }),
+            35 + 617 + 598
+        );
+        assert_eq!(
+            rule(|r| r.adjacency = Adjacency::Radius(1)),
+            rule(|r| r.adjacency = Adjacency::Diagonal)
+        );
+        assert_eq!(Adjacency::Radius(2).offsets().len(), 24);
+    }
+
+    #[test]
+    fn render_test() {
+        let example_input =
+            common::split_string("467..114..\n...*......\n..35.#633.\n..*.......".into());
+        let map = EngineMap::read_map(&example_input);
+        assert_eq!(
+            map.render(&GearRule::default(), false),
+            "[467]..114..\n...{*}......\n..[35].#[633].\n..*......."
+        );
+        let colored = map.render(&GearRule::default(), true);
+        assert_eq!(
+            colored.lines().next().unwrap(),
+            "\x1b[1;32m467\x1b[0m..\x1b[31m114\x1b[0m.."
+        );
+        assert_eq!(colored.lines().nth(3).unwrap(), "..\x1b[36m*\x1b[0m.......");
+    }
+
+    #[test]
+    fn clusters_test() {
+        let example_input = common::split_string(
+            "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598.."
+                .into(),
+        );
+        let map = EngineMap::read_map(&example_input);
+        let graph = PartGraph::new(&map);
+        let cluster_vals: Vec<Vec<u64>> = graph
+            .clusters()
+            .iter()
+            .map(|c| c.numbers.iter().map(|k| map.numbers[*k].val).collect())
+            .collect();
+        assert_eq!(
+            cluster_vals,
+            vec![
+                vec![467, 35],
+                vec![633],
+                vec![617],
+                vec![592],
+                vec![755, 598],
+                vec![664]
+            ]
+        );
+        assert_eq!(
+            PartGraph::largest_cluster(&graph.clusters()),
+            Some(&Cluster {
+                numbers: vec![0, 2],
+                symbols: vec![0]
+            })
+        );
+        assert!(graph.numbers_with_several_symbols().is_empty());
+        assert!(graph.lonely_symbols().is_empty());
+        assert!(graph
+            .report(&map)
+            .ends_with("Symbols with no numbers: none"));
+    }
+
+    #[test]
+    fn shared_and_lonely_symbols_test() {
+        let map = EngineMap::read_map(&common::split_string("12.\n#*.\n...\n.\"$".into()));
+        let graph = PartGraph::new(&map);
+        assert_eq!(
+            graph.clusters(),
+            vec![Cluster {
+                numbers: vec![0],
+                symbols: vec![0, 1]
+            }]
+        );
+        assert_eq!(graph.numbers_with_several_symbols(), vec![0]);
+        assert_eq!(graph.lonely_symbols(), vec![2, 3]);
+        assert_eq!(
+            graph.report(&map),
+            "1 number (1 part number), 4 symbols, 1 cluster
+Clusters sharing a symbol: 0
+Largest cluster: numbers 12; symbols # at (1, 0), * at (1, 1)
+Numbers next to several symbols: 12
+Symbols with no numbers: \" at (3, 1), $ at (3, 2)"
+        );
+        assert_eq!(
+            graph.to_json(&map),
+            concat!(
+                r##"{"numbers":[{"id":0,"value":12,"row":0,"col":0,"symbols":[0,1]}],"##,
+                r##""symbols":[{"id":0,"symbol":"#","row":1,"col":0,"numbers":[0]},"##,
+                r##"{"id":1,"symbol":"*","row":1,"col":1,"numbers":[0]},"##,
+                r##"{"id":2,"symbol":"\"","row":3,"col":1,"numbers":[]},"##,
+                r##"{"id":3,"symbol":"$","row":3,"col":2,"numbers":[]}],"##,
+                r##""clusters":[{"numbers":[0],"symbols":[0,1]}]}"##
+            )
+        );
+    }
 }
@@ -3,38 +3,166 @@
  * Christopher Phan
  */
 
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::error::Error;
-use std::str::FromStr;
+use std::io::BufRead;
+use std::iter;
 
 use crate::common;
+use crate::common::checked;
+use crate::common::parse::{
+    delimited, literal, many, map, pair, parse_all, terminated, token, uint, Parser,
+};
 use crate::common::AdventError;
+use crate::common::BigUint;
 
 pub fn run() {
     let day4_input = common::get_day(4).unwrap();
     println!("{}", common::soln_output(4, 1, part_1(day4_input.clone())));
-    println!("{}", common::soln_output(4, 2, part_2(day4_input)));
+    println!(
+        "{}",
+        common::soln_output(4, 2, part_2_big(day4_input).unwrap())
+    );
 }
 
 pub fn part_1(input: Vec<String>) -> u64 {
+    part_1_with_rule(input, &Standard)
+}
+
+pub fn part_1_with_rule(input: Vec<String>, rule: &dyn ScoringRule) -> u64 {
+    let _part = checked::solving(4, 1);
     let cards = Card::read_input(input);
-    cards.iter().map(|k| k.value()).sum()
+    checked::sum(cards.iter().map(|k| k.value(rule)), "sum of card values")
 }
 
-pub fn part_2(input: Vec<String>) -> u64 {
-    let cards: Vec<SimplifiedCard> = Card::read_input(input)
-        .iter()
-        .cloned()
-        .map(|k| k.into())
-        .collect();
-    let number_of_cards = cards.len();
-    let mut card_quant: Vec<u64> = cards.iter().map(|_| 1).collect();
-    let card_no = CardNumbering(cards);
-    for c in 0..number_of_cards {
-        for k in card_no.get(c + 1).unwrap().0 {
-            card_quant[k - 1] += card_quant[c];
+pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(4, 2);
+    let card_no = CardNumbering::read_input(input, &Standard, PastTablePolicy::default())?;
+    Ok(card_no
+        .total_cards(0, 1, |a, b| Some(checked::add(*a, *b, "total_cards")))
+        .unwrap())
+}
+
+/* Same as part_2, but falls back to arbitrary precision if the number of cards doesn't fit in a
+ * u64 */
+pub fn part_2_big(input: Vec<String>) -> Result<BigUint, Box<dyn Error>> {
+    part_2_with(input, &Standard, PastTablePolicy::default())
+}
+
+pub fn part_2_with(
+    input: Vec<String>,
+    rule: &dyn ScoringRule,
+    policy: PastTablePolicy,
+) -> Result<BigUint, Box<dyn Error>> {
+    let _part = checked::solving(4, 2);
+    let card_no = CardNumbering::read_input(input, rule, policy)?;
+    Ok(
+        match card_no.total_cards(0_u64, 1_u64, |a, b| a.checked_add(*b)) {
+            Some(k) => BigUint::from(k),
+            None => card_no
+                .total_cards(BigUint::zero(), BigUint::one(), |a, b| Some(a + b))
+                .unwrap(),
+        },
+    )
+}
+
+/* Same as part_1 and part_2 (with the puzzle's rules), but reading the cards one line at a time
+ * instead of holding them all in memory. Part 2 needs the cards in increasing order of id, and
+ * only keeps the copies won of the next few cards. Blank lines are skipped, and any other line
+ * that isn't a card is an error. */
+pub fn part_1_stream(reader: impl BufRead) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(4, 1);
+    let mut total: u64 = 0;
+    stream_cards(reader, |_, matches| {
+        total = checked::add(total, Standard.points(matches), "sum of card values");
+        Ok(())
+    })?;
+    Ok(total)
+}
+
+pub fn part_2_stream(reader: impl BufRead) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(4, 2);
+    // Copies won so far of the cards following the last one read
+    let mut pending: VecDeque<u64> = VecDeque::new();
+    let mut last_id: Option<usize> = None;
+    let mut total: u64 = 0;
+    stream_cards(reader, |id, matches| {
+        if let Some(prev) = last_id {
+            if id <= prev {
+                return Err(AdventError(format!(
+                    "Card {} comes after card {}",
+                    id, prev
+                )));
+            }
+            // Copies of missing cards are never won
+            let missing = (id - prev - 1).min(pending.len());
+            pending.drain(..missing);
+        }
+        let copies = checked::add(1, pending.pop_front().unwrap_or(0), "total_cards");
+        total = checked::add(total, copies, "total_cards");
+        if pending.len() < matches as usize {
+            pending.resize(matches as usize, 0);
         }
+        for k in pending.iter_mut().take(matches as usize) {
+            *k = checked::add(*k, copies, "total_cards");
+        }
+        last_id = Some(id);
+        Ok(())
+    })?;
+    Ok(total)
+}
+
+fn stream_cards(
+    mut reader: impl BufRead,
+    mut f: impl FnMut(usize, u32) -> Result<(), AdventError>,
+) -> Result<(), Box<dyn Error>> {
+    let mut line = String::new();
+    let mut line_number: usize = 0;
+    while reader.read_line(&mut line)? > 0 {
+        line_number += 1;
+        if !line.trim().is_empty() {
+            let card = parse_all(Card::parser(), &line)
+                .map_err(|e| AdventError(format!("line {}: {}", line_number, e)))?;
+            f(card.id, card.num_matches())?;
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+// A set of numbers, using a bitset for the small ones
+#[derive(Debug, Default)]
+struct NumberSet {
+    small: u128,
+    large: HashSet<u64>,
+}
+
+impl NumberSet {
+    fn insert(&mut self, k: u64) {
+        if k < 128 {
+            self.small |= 1 << k;
+        } else {
+            self.large.insert(k);
+        }
+    }
+
+    fn contains(&self, k: u64) -> bool {
+        if k < 128 {
+            self.small & (1 << k) != 0
+        } else {
+            self.large.contains(&k)
+        }
+    }
+}
+
+impl FromIterator<u64> for NumberSet {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let mut output = Self::default();
+        for k in iter {
+            output.insert(k);
+        }
+        output
     }
-    card_quant.iter().sum()
 }
 
 #[derive(Debug, Clone)]
@@ -45,87 +173,358 @@ struct Card {
 }
 
 impl Card {
+    // e.g. "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53"
+    fn parser() -> impl Parser<Self> {
+        map(
+            pair(
+                delimited(
+                    token(literal("Card")),
+                    token(uint::<usize>),
+                    token(literal(":")),
+                ),
+                pair(
+                    terminated(many(token(uint::<u64>)), token(literal("|"))),
+                    many(token(uint::<u64>)),
+                ),
+            ),
+            |(id, (left_side, right_side))| Self {
+                id,
+                left_side,
+                right_side,
+            },
+        )
+    }
+
     fn read_line(s: String) -> Result<Self, Box<dyn Error>> {
-        let parts: Vec<String> = s.split([':', '|']).map(|k| k.to_string()).collect();
-        let parts0: Vec<String> = parts
-            .get(0)
-            .ok_or(AdventError("Malformed card".into()))?
-            .split_whitespace()
-            .map(|k| k.to_string())
-            .collect();
-        let id: usize = parts0
-            .get(1)
-            .ok_or(AdventError("Malformed card".into()))?
-            .parse()?;
-        let left_side: Vec<u64> = parts
-            .get(1)
-            .ok_or(AdventError("Malformed card".into()))?
-            .split_whitespace()
-            .map(|k| u64::from_str(k))
-            .filter(|k| k.is_ok())
-            .map(|k| k.unwrap())
-            .collect();
-        let right_side: Vec<u64> = parts
-            .get(2)
-            .ok_or(AdventError("Malformed card".into()))?
-            .split_whitespace()
-            .map(|k| u64::from_str(k))
-            .filter(|k| k.is_ok())
-            .map(|k| k.unwrap())
-            .collect();
-        Ok(Self {
-            id,
-            left_side,
-            right_side,
-        })
+        Ok(parse_all(Self::parser(), &s)?)
     }
 
     fn read_input(input: Vec<String>) -> Vec<Self> {
         input
             .iter()
-            .map(|k| Card::read_line(k.to_string()))
-            .filter(|k| k.is_ok())
-            .map(|k| k.unwrap())
+            .flat_map(|k| Card::read_line(k.to_string()))
             .collect()
     }
 
     fn num_matches(&self) -> u32 {
+        let winning: NumberSet = self.left_side.iter().copied().collect();
         self.right_side
             .iter()
-            .filter(|k| self.left_side.contains(k))
+            .filter(|k| winning.contains(**k))
             .count() as u32
     }
 
-    fn value(&self) -> u64 {
-        let m = self.num_matches();
-        match m {
+    fn value(&self, rule: &dyn ScoringRule) -> u64 {
+        rule.points(self.num_matches())
+    }
+}
+
+/// How a scratchcard with a given number of matches is scored: how many points it's worth (part
+/// 1), and which cards it wins copies of (part 2).
+pub trait ScoringRule {
+    /// By default, 1 point for the first match, doubled for each further match.
+    fn points(&self, matches: u32) -> u64 {
+        match matches {
             0 => 0,
-            k => 2_u64.pow(k - 1),
+            k => checked::pow(2_u64, k - 1, "card value"),
         }
     }
+
+    /// The ids of the cards that one copy of card `id` wins a copy of; an id listed twice wins
+    /// two copies. Every id must be greater than `id`, since copies are handed out in order of
+    /// id: a card can't win copies of itself or of earlier cards. The ids must not wrap around:
+    /// an id too large for a `usize` is returned as `None` (which is past the end of any table),
+    /// and nothing comes after it.
+    fn cards_won(&self, id: usize, matches: u32) -> Vec<Option<usize>>;
 }
 
+/// The puzzle's rule: win a copy of each of the next `matches` cards.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Standard;
+
+impl ScoringRule for Standard {
+    fn cards_won(&self, id: usize, matches: u32) -> Vec<Option<usize>> {
+        until_overflow((1..=(matches as usize)).map(|k| id.checked_add(k)))
+    }
+}
+
+/// Win a copy of every `n`-th card after this one, `matches` of them, e.g. with a stride of 2,
+/// card 1 with 3 matches wins cards 3, 5 and 7.
+#[derive(Copy, Clone, Debug)]
+pub struct Stride(usize);
+
+impl Stride {
+    /// A stride of `n`, which must be positive (a stride of 0 would have cards winning themselves).
+    pub fn new(n: usize) -> Result<Self, AdventError> {
+        if n == 0 {
+            Err(AdventError("stride must be positive".into()))
+        } else {
+            Ok(Self(n))
+        }
+    }
+}
+
+impl ScoringRule for Stride {
+    fn cards_won(&self, id: usize, matches: u32) -> Vec<Option<usize>> {
+        until_overflow(
+            (1..=(matches as usize)).map(|k| k.checked_mul(self.0).and_then(|k| id.checked_add(k))),
+        )
+    }
+}
+
+/// Win `matches` copies of the next card, and a point per match.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Proportional;
+
+impl ScoringRule for Proportional {
+    fn points(&self, matches: u32) -> u64 {
+        matches.into()
+    }
+
+    fn cards_won(&self, id: usize, matches: u32) -> Vec<Option<usize>> {
+        until_overflow(iter::repeat(id.checked_add(1)).take(matches as usize))
+    }
+}
+
+// The ids up to the first one that doesn't fit in a usize (included, as `None`)
+fn until_overflow(ids: impl Iterator<Item = Option<usize>>) -> Vec<Option<usize>> {
+    let mut out_vec: Vec<Option<usize>> = vec![];
+    for k in ids {
+        out_vec.push(k);
+        if k.is_none() {
+            break;
+        }
+    }
+    out_vec
+}
+
+// A card's id and the ids of the cards it wins copies of
 #[derive(Debug, Clone)]
-struct SimplifiedCard(Vec<usize>);
+struct SimplifiedCard {
+    id: usize,
+    to_win: Vec<Option<usize>>,
+}
 
-impl From<Card> for SimplifiedCard {
-    fn from(card: Card) -> Self {
-        let to_win: Vec<usize> = (0..(card.num_matches() as usize))
-            .map(|k| card.id + k + 1)
-            .collect();
-        Self(to_win)
+impl SimplifiedCard {
+    fn new(card: &Card, rule: &dyn ScoringRule) -> Self {
+        Self {
+            id: card.id,
+            to_win: rule.cards_won(card.id, card.num_matches()),
+        }
     }
 }
 
-struct CardNumbering(Vec<SimplifiedCard>);
+/// What to do with a card that wins copies of cards past the end of the table (which the puzzle
+/// promises won't happen), including ids too large for a `usize`. Copies of ids missing from the
+/// middle of the table are never won.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PastTablePolicy {
+    /// The card wins copies of the cards up to the end of the table
+    #[default]
+    Clamp,
+    /// The card wins no copies at all
+    Ignore,
+    Error,
+}
+
+/* The cards sorted by id, with the cards each one wins copies of, as positions in the sorted
+ * list. Copies only go to cards with larger ids (`new` checks this), so going through the list in
+ * order works. */
+struct CardNumbering {
+    ids: Vec<usize>,
+    wins: Vec<Vec<usize>>,
+}
 
 impl CardNumbering {
-    fn get(&self, card_id: usize) -> Result<SimplifiedCard, Box<dyn Error>> {
-        Ok(self
-            .0
-            .get(card_id - 1)
-            .ok_or(AdventError("Card not found".to_string()))?
-            .clone())
+    fn new(cards: Vec<SimplifiedCard>, policy: PastTablePolicy) -> Result<Self, AdventError> {
+        let mut cards = cards;
+        cards.sort_by_key(|k| k.id);
+        let ids: Vec<usize> = cards.iter().map(|k| k.id).collect();
+        if let Some(w) = ids.windows(2).find(|w| w[0] == w[1]) {
+            return Err(AdventError(format!("Card {} appears more than once", w[0])));
+        }
+        let last_id = ids.last().copied().unwrap_or(0);
+        let mut wins: Vec<Vec<usize>> = vec![];
+        for card in cards {
+            let mut to_win = card.to_win;
+            if let Some(k) = to_win.iter().flatten().find(|k| **k <= card.id) {
+                return Err(AdventError(format!(
+                    "Card {} wins a copy of card {}, which doesn't come after it",
+                    card.id, k
+                )));
+            }
+            if to_win.iter().any(|k| k.map_or(true, |k| k > last_id)) {
+                match policy {
+                    PastTablePolicy::Clamp => to_win.retain(|k| k.is_some_and(|k| k <= last_id)),
+                    PastTablePolicy::Ignore => to_win.clear(),
+                    PastTablePolicy::Error => {
+                        return Err(AdventError(format!(
+                            "Card {} wins copies past the last card, {}",
+                            card.id, last_id
+                        )))
+                    }
+                }
+            }
+            wins.push(
+                to_win
+                    .iter()
+                    .flatten()
+                    .filter_map(|k| ids.binary_search(k).ok())
+                    .collect(),
+            );
+        }
+        Ok(Self { ids, wins })
+    }
+
+    fn read_input(
+        input: Vec<String>,
+        rule: &dyn ScoringRule,
+        policy: PastTablePolicy,
+    ) -> Result<Self, AdventError> {
+        Self::new(
+            Card::read_input(input)
+                .iter()
+                .map(|k| SimplifiedCard::new(k, rule))
+                .collect(),
+            policy,
+        )
+    }
+
+    // Number of copies held at the end of each card (in the order of `ids`), or the position of
+    // the first card whose count `add` fails on
+    fn copy_counts<T: Clone>(
+        &self,
+        one: T,
+        add: impl Fn(&T, &T) -> Option<T>,
+    ) -> Result<Vec<T>, usize> {
+        let mut card_quant: Vec<T> = vec![one; self.ids.len()];
+        for (c, won) in self.wins.iter().enumerate() {
+            for k in won {
+                card_quant[*k] = add(&card_quant[*k], &card_quant[c]).ok_or(*k)?;
+            }
+        }
+        Ok(card_quant)
+    }
+
+    // Total number of cards held at the end, or None if `add` fails
+    fn total_cards<T: Clone>(
+        &self,
+        zero: T,
+        one: T,
+        add: impl Fn(&T, &T) -> Option<T>,
+    ) -> Option<T> {
+        self.copy_counts(one, &add)
+            .ok()?
+            .iter()
+            .try_fold(zero, |total, k| add(&total, k))
+    }
+}
+
+/// How one card fared in part 2.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CardTrace {
+    pub id: usize,
+    pub matches: u32,
+    pub points: u64,
+    /// Number of copies held at the end, including the original
+    pub copies: u64,
+    /// The earlier cards that won copies of this one, with how many copies each won
+    pub contributors: Vec<(usize, u64)>,
+}
+
+/// The trace of each card, in order of id.
+pub fn trace(
+    input: Vec<String>,
+    rule: &dyn ScoringRule,
+    policy: PastTablePolicy,
+) -> Result<Vec<CardTrace>, Box<dyn Error>> {
+    let cards = Card::read_input(input);
+    let matches: BTreeMap<usize, u32> = cards.iter().map(|k| (k.id, k.num_matches())).collect();
+    let card_no = CardNumbering::new(
+        cards.iter().map(|k| SimplifiedCard::new(k, rule)).collect(),
+        policy,
+    )?;
+    let copies = card_no
+        .copy_counts(1_u64, |a, b| a.checked_add(*b))
+        .map_err(|k| AdventError(format!("Too many copies of card {}", card_no.ids[k])))?;
+    let mut contributors: Vec<Vec<(usize, u64)>> = vec![vec![]; card_no.ids.len()];
+    for (c, won) in card_no.wins.iter().enumerate() {
+        let id = card_no.ids[c];
+        for k in won {
+            match contributors[*k].last_mut() {
+                Some((from, n)) if *from == id => *n += copies[c],
+                _ => contributors[*k].push((id, copies[c])),
+            }
+        }
+    }
+    Ok(card_no
+        .ids
+        .iter()
+        .zip(copies)
+        .zip(contributors)
+        .map(|((id, copies), contributors)| CardTrace {
+            id: *id,
+            matches: matches[id],
+            points: rule.points(matches[id]),
+            copies,
+            contributors,
+        })
+        .collect())
+}
+
+/// One row per card, with the contributors as `id:copies` separated by semicolons.
+pub fn trace_csv(traces: &[CardTrace]) -> String {
+    let mut lines: Vec<String> = vec!["card,matches,points,copies,contributors".into()];
+    for t in traces {
+        let contributors: Vec<String> = t
+            .contributors
+            .iter()
+            .map(|(id, n)| format!("{}:{}", id, n))
+            .collect();
+        lines.push(format!(
+            "{},{},{},{},{}",
+            t.id,
+            t.matches,
+            t.points,
+            t.copies,
+            contributors.join(";")
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Print the trace of each card of the input, as a table or as CSV.
+pub fn run_trace(csv: bool) {
+    let day4_input = common::get_day(4).unwrap();
+    let traces = match trace(day4_input, &Standard, PastTablePolicy::default()) {
+        Ok(k) => k,
+        Err(e) => {
+            println!("Could not trace the cards: {}", e);
+            return;
+        }
+    };
+    if csv {
+        println!("{}", trace_csv(&traces));
+    } else {
+        println!(
+            "{:>6} {:>7} {:>10} {:>12}  from",
+            "card", "matches", "points", "copies"
+        );
+        for t in traces.iter() {
+            let contributors: Vec<String> = t
+                .contributors
+                .iter()
+                .map(|(id, n)| format!("{} (x{})", id, n))
+                .collect();
+            println!(
+                "{:>6} {:>7} {:>10} {:>12}  {}",
+                t.id,
+                t.matches,
+                t.points,
+                t.copies,
+                contributors.join(", ")
+            );
+        }
     }
 }
 
@@ -133,6 +532,89 @@ impl CardNumbering {
 mod tests {
     use super::*;
     use crate::common;
+    use crate::common::rng::Lcg;
+    use std::io::Cursor;
+    use std::time::Instant;
+
+    // Pseudo-random cards with 5 winning numbers and 8 numbers each, all below 100, so that the
+    // number of copies stays small
+    fn generated_cards(count: usize) -> String {
+        let mut rng = Lcg::new(0x2023_1204);
+        let mut next = || (rng.below(99) + 1).to_string();
+        let lines: Vec<String> = (1..=count)
+            .map(|id| {
+                let left: Vec<String> = (0..5).map(|_| next()).collect();
+                let right: Vec<String> = (0..8).map(|_| next()).collect();
+                format!("Card {}: {} | {}", id, left.join(" "), right.join(" "))
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    #[test]
+    fn stream_test() {
+        let input = generated_cards(2000);
+        let lines = common::split_string(input.clone());
+        assert_eq!(
+            part_1_stream(Cursor::new(&input)).unwrap(),
+            part_1(lines.clone())
+        );
+        assert_eq!(
+            part_2_stream(Cursor::new(&input)).unwrap(),
+            part_2(lines).unwrap()
+        );
+        // Card 3 wins a copy of card 4, which is missing; card 5 wins copies of cards 6 and 7
+        let input = "Card 3: 1 | 1\nCard 5: 1 2 | 1 2\n\nCard 6: 5 | 5\nCard 9: 7 | 7\n";
+        assert_eq!(part_2_stream(Cursor::new(input)).unwrap(), 5);
+        assert!(part_2_stream(Cursor::new("Card 2: 1 | 1\nCard 1: 1 | 1")).is_err());
+        // Leading whitespace is fine, as with `Card::parser`, but lines that aren't cards aren't
+        assert_eq!(
+            part_1_stream(Cursor::new("  Card 1: 1 2 | 2 1")).unwrap(),
+            2
+        );
+        let e = part_1_stream(Cursor::new("Card 1: 1 | 1\nCrad 2: 1 | 1")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2: parse error at position 0: expected \"Card\""
+        );
+    }
+
+    #[test]
+    fn large_numbers_test() {
+        let card = Card::read_line("Card 1: 5 200 1000 | 1000 5 7 200 200".into()).unwrap();
+        assert_eq!(card.num_matches(), 4);
+    }
+
+    /* Run with `cargo test --release -- --ignored --nocapture stream_benchmark` */
+    #[test]
+    #[ignore]
+    fn stream_benchmark() {
+        let input = generated_cards(1_000_000);
+        let start = Instant::now();
+        let lines = common::split_string(input.clone());
+        let cards = Card::read_input(lines.clone());
+        let naive: usize = cards
+            .iter()
+            .map(|c| {
+                c.right_side
+                    .iter()
+                    .filter(|k| c.left_side.contains(k))
+                    .count()
+            })
+            .sum();
+        println!("read and match with Vec::contains: {:?}", start.elapsed());
+        let start = Instant::now();
+        let in_memory = (part_1(lines.clone()), part_2(lines).unwrap());
+        println!("part_1 and part_2: {:?}", start.elapsed());
+        let start = Instant::now();
+        let streamed = (
+            part_1_stream(Cursor::new(&input)).unwrap(),
+            part_2_stream(Cursor::new(&input)).unwrap(),
+        );
+        println!("part_1_stream and part_2_stream: {:?}", start.elapsed());
+        assert_eq!(in_memory, streamed);
+        assert!(naive > 0);
+    }
 
     #[test]
     fn part1_test() {
@@ -159,6 +641,196 @@ Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
 Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"
                 .into(),
         );
-        assert_eq!(part_2(example_input), 30);
+        assert_eq!(part_2(example_input).unwrap(), 30);
+    }
+
+    #[test]
+    fn part2_big_test() {
+        // Card k wins a copy of every later card, so there are 2^(k - 1) copies of card k
+        let input: Vec<String> = (1..=70)
+            .map(|k| {
+                let nums: Vec<String> = (1..=(70 - k)).map(|j| j.to_string()).collect();
+                format!("Card {}: {} | {}", k, nums.join(" "), nums.join(" "))
+            })
+            .collect();
+        assert_eq!(
+            part_2_big(input).unwrap(),
+            &BigUint::from(2_u64).pow(70) - &BigUint::one()
+        );
+    }
+
+    // A card with the given id and number of matches
+    fn card_line(id: usize, matches: usize) -> String {
+        let nums: Vec<String> = (1..=matches).map(|k| k.to_string()).collect();
+        format!("Card {}: {} 99 | {} 98", id, nums.join(" "), nums.join(" "))
+    }
+
+    #[test]
+    fn sparse_ids_test() {
+        // Card 3 wins a copy of card 4, which doesn't exist; card 5 wins copies of 6 and 7
+        let input = vec![
+            card_line(5, 2),
+            card_line(3, 1),
+            card_line(9, 0),
+            card_line(6, 1),
+        ];
+        assert_eq!(part_2(input).unwrap(), 5);
+        let input = vec![card_line(2, 1), card_line(2, 0)];
+        assert!(part_2(input).is_err());
+    }
+
+    #[test]
+    fn past_table_policy_test() {
+        // Card 8 would win copies of cards 9 and 10
+        let input = vec![card_line(7, 1), card_line(8, 2), card_line(9, 0)];
+        let total = |policy| {
+            part_2_with(input.clone(), &Standard, policy)
+                .ok()
+                .map(|k| k.to_string())
+        };
+        assert_eq!(total(PastTablePolicy::Clamp), Some("6".into()));
+        assert_eq!(total(PastTablePolicy::Ignore), Some("4".into()));
+        assert_eq!(total(PastTablePolicy::Error), None);
+    }
+
+    #[test]
+    fn max_id_test() {
+        // Card usize::MAX - 1 would win copies of card usize::MAX and of the card after it, whose
+        // id doesn't fit in a usize
+        let input = vec![card_line(usize::MAX - 1, 2), card_line(usize::MAX, 1)];
+        let total = |policy| {
+            part_2_with(input.clone(), &Standard, policy)
+                .ok()
+                .map(|k| k.to_string())
+        };
+        assert_eq!(total(PastTablePolicy::Clamp), Some("3".into()));
+        assert_eq!(total(PastTablePolicy::Ignore), Some("2".into()));
+        assert_eq!(total(PastTablePolicy::Error), None);
+        let input = vec![format!("Card {}: 1 | 1", usize::MAX)];
+        assert_eq!(part_2(input).unwrap(), 1);
+    }
+
+    #[test]
+    fn part2_empty_test() {
+        assert_eq!(part_2(vec![]).unwrap(), 0);
+        assert_eq!(part_2_big(vec![]).unwrap(), BigUint::zero());
+    }
+
+    #[test]
+    fn scoring_rule_test() {
+        let example_input = common::split_string(
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"
+                .into(),
+        );
+        // Matches: 4, 2, 2, 1, 0, 0
+        let total = |rule: &dyn ScoringRule| {
+            part_2_with(example_input.clone(), rule, PastTablePolicy::Clamp)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(total(&Standard), "30");
+        assert_eq!(total(&Stride::new(1).unwrap()), "30");
+        // Card 1 wins 3 and 5 (7 and 9 are past the table), card 2 wins 4 and 6, card 3 wins 5
+        // and card 4 wins 6
+        assert_eq!(total(&Stride::new(2).unwrap()), "14");
+        // Card 1: 1 copy, wins 4 of card 2 (5 copies), which win 10 of card 3 (11 copies), ...
+        assert_eq!(total(&Proportional), (1 + 5 + 11 + 23 + 24 + 1).to_string());
+        assert_eq!(part_1_with_rule(example_input.clone(), &Proportional), 9);
+        assert_eq!(
+            part_1_with_rule(example_input, &Stride::new(3).unwrap()),
+            13
+        );
+    }
+
+    #[test]
+    fn overflowing_rules_test() {
+        let stride = Stride::new(usize::MAX).unwrap();
+        assert_eq!(stride.cards_won(0, 3), vec![Some(usize::MAX), None]);
+        assert_eq!(stride.cards_won(1, 3), vec![None]);
+        assert_eq!(Proportional.cards_won(usize::MAX, 2), vec![None]);
+        assert_eq!(
+            Standard.cards_won(usize::MAX - 1, 3),
+            vec![Some(usize::MAX), None]
+        );
+        let input = vec![card_line(1, 2), card_line(2, 0)];
+        assert_eq!(
+            part_2_with(input.clone(), &stride, PastTablePolicy::Clamp).unwrap(),
+            BigUint::from(2_u64)
+        );
+        assert!(part_2_with(input, &stride, PastTablePolicy::Error).is_err());
+    }
+
+    // Wins a copy of the card before this one
+    struct Backwards;
+
+    impl ScoringRule for Backwards {
+        fn cards_won(&self, id: usize, matches: u32) -> Vec<Option<usize>> {
+            vec![Some(id - 1); matches as usize]
+        }
+    }
+
+    #[test]
+    fn earlier_card_test() {
+        assert!(Stride::new(0).is_err());
+        let input = vec![card_line(1, 0), card_line(2, 1)];
+        let e = part_2_with(input, &Backwards, PastTablePolicy::Clamp).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Card 2 wins a copy of card 1, which doesn't come after it"
+        );
+    }
+
+    #[test]
+    fn trace_test() {
+        let example_input = common::split_string(
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"
+                .into(),
+        );
+        let traces = trace(example_input.clone(), &Standard, PastTablePolicy::Clamp).unwrap();
+        assert_eq!(
+            traces[3],
+            CardTrace {
+                id: 4,
+                matches: 1,
+                points: 1,
+                copies: 8,
+                contributors: vec![(1, 1), (2, 2), (3, 4)]
+            }
+        );
+        assert_eq!(
+            traces.iter().map(|k| k.copies).sum::<u64>(),
+            part_2(example_input.clone()).unwrap()
+        );
+        assert_eq!(
+            trace_csv(&traces),
+            "card,matches,points,copies,contributors
+1,4,8,1,
+2,2,2,2,1:1
+3,2,2,4,1:1;2:2
+4,1,1,8,1:1;2:2;3:4
+5,0,0,14,1:1;3:4;4:8
+6,0,0,1,"
+        );
+        let traces = trace(example_input, &Proportional, PastTablePolicy::Clamp).unwrap();
+        assert_eq!(traces[1].contributors, vec![(1, 4)]);
+        assert_eq!(traces[1].points, 2);
+        // As in part2_big_test, card k ends with 2^(k - 1) copies
+        let input: Vec<String> = (1..=70).map(|k| card_line(k, 70 - k)).collect();
+        assert_eq!(
+            trace(input, &Standard, PastTablePolicy::Clamp)
+                .unwrap_err()
+                .to_string(),
+            "Too many copies of card 65"
+        );
     }
 }
@@ -3,12 +3,17 @@
  * Christopher Phan
  */
 
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::num::NonZeroUsize;
-use std::str::FromStr;
 
 use crate::common;
+use crate::common::checked;
+use crate::common::parse::{
+    literal, many, pair, parse_all, preceded, section, terminated, token, uint, word,
+};
 use crate::common::AdventError;
+use crate::common::IntervalSet;
 
 pub fn run() {
     let input = common::get_day(5).unwrap();
@@ -28,296 +33,540 @@ pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
 }
 
 pub fn part(input: Vec<String>, part2: bool) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(5, if part2 { 2 } else { 1 });
     let (seeds, map_stack) = parse_input(input, part2)?;
-    match map_stack.apply(&seeds).min() {
+    // Part 1 only has a handful of seeds, so look each one up in the composed map
+    let location = if part2 {
+        map_stack.apply(&seeds).min()
+    } else {
+        let map = map_stack.compose();
+        seeds.iter().map(|k| map.eval(k)).min()
+    };
+    match location {
         Some(k) => Ok(k as u64),
         None => Err(Box::new(AdventError("No locations".into()))),
     }
 }
 
+// e.g. "seeds: 79 14 55 13"
+fn read_seeds(line: &str, part2: bool) -> Result<IntervalSet<usize>, Box<dyn Error>> {
+    let input_numbers: Vec<usize> = parse_all(section("seeds", many(token(uint::<usize>))), line)?;
+    if part2 {
+        let ranges = input_numbers
+            .chunks_exact(2)
+            .filter(|k| k[1] > 0)
+            .map(|k| match k[0].checked_add(k[1] - 1) {
+                Some(last) => Ok(k[0]..=last),
+                None => Err(AdventError("seed range out of bounds".into())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(IntervalSet::from_ranges(ranges))
+    } else {
+        Ok(input_numbers.into_iter().collect())
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
-struct IntegerInterval {
-    first: usize,
-    length: NonZeroUsize,
+struct RangeShift {
+    source_first: usize,
+    source_last: usize,
+    destination_start: usize,
 }
 
-impl IntegerInterval {
-    fn single(val: usize) -> Self {
-        Self {
-            first: val,
-            length: NonZeroUsize::new(1).unwrap(),
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct RangeShiftResult {
+    image: IntervalSet<usize>,
+    not_mapped: IntervalSet<usize>,
+}
+
+impl RangeShift {
+    fn source(&self) -> IntervalSet<usize> {
+        IntervalSet::from_range(self.source_first..=self.source_last)
+    }
+
+    fn apply_to_set(&self, val: &IntervalSet<usize>) -> RangeShiftResult {
+        let source = self.source();
+        let overlap = val.intersection(&source);
+        let image = if self.destination_start >= self.source_first {
+            overlap.shift_up(self.destination_start - self.source_first)
+        } else {
+            overlap.shift_down(self.source_first - self.destination_start)
+        };
+        RangeShiftResult {
+            image,
+            not_mapped: val.difference(&source),
         }
     }
 
-    fn last(&self) -> usize {
-        self.first + self.length.get() - 1
+    fn offset(&self) -> i128 {
+        self.destination_start as i128 - self.source_first as i128
     }
 
-    fn overlapping(&self, other: &Self) -> bool {
-        let max_first = self.first.max(other.first);
-        let min_last = self.last().min(other.last());
-        max_first <= min_last
+    fn destination(&self) -> IntervalSet<usize> {
+        IntervalSet::from_range(
+            self.destination_start
+                ..=(self.destination_start + (self.source_last - self.source_first)),
+        )
     }
 
-    fn overlap(&self, other: &Self) -> Option<Self> {
-        let max_first = self.first.max(other.first);
-        let min_last = self.last().min(other.last());
-        if max_first <= min_last {
-            let length = NonZeroUsize::new(min_last - max_first + 1).unwrap();
-            Some(Self {
-                first: max_first,
-                length,
-            })
+    // The values in the source that this shift moves into `val`
+    fn invert(&self, val: &IntervalSet<usize>) -> IntervalSet<usize> {
+        let overlap = val.intersection(&self.destination());
+        if self.destination_start >= self.source_first {
+            overlap.shift_down(self.destination_start - self.source_first)
         } else {
-            None
+            overlap.shift_up(self.source_first - self.destination_start)
         }
     }
 
-    fn set_minus(&self, other: &Self) -> Vec<Self> {
-        match self.overlap(other) {
-            Some(interval) => {
-                let mut out_vec: Vec<Self> = vec![];
-                if self.first < interval.first {
-                    out_vec.push(Self {
-                        first: self.first,
-                        length: NonZeroUsize::new(interval.first - self.first).unwrap(),
-                    });
-                }
-                if self.last() > interval.last() {
-                    out_vec.push(Self {
-                        first: interval.last() + 1,
-                        length: NonZeroUsize::new(self.last() - interval.last()).unwrap(),
-                    });
-                }
-                out_vec
-            }
-            None => vec![self.clone()],
-        }
+    fn read_line(s: String) -> Result<Self, Box<dyn Error>> {
+        // e.g. "50 98 2"
+        let (destination_start, (source_first, length)) = parse_all(
+            pair(
+                token(uint::<usize>),
+                pair(token(uint::<usize>), token(uint::<usize>)),
+            ),
+            &s,
+        )?;
+        let length = NonZeroUsize::new(length).ok_or(AdventError("zero-length range".into()))?;
+        let source_last = source_first
+            .checked_add(length.get() - 1)
+            .ok_or(AdventError("range out of bounds".into()))?;
+        destination_start
+            .checked_add(length.get() - 1)
+            .ok_or(AdventError("range out of bounds".into()))?;
+
+        Ok(RangeShift {
+            source_first,
+            source_last,
+            destination_start,
+        })
     }
+}
 
-    fn combine(&self, other: &Self) -> Vec<Self> {
-        let max_first = self.first.max(other.first);
-        let min_last = self.last().min(other.last());
-        if max_first <= min_last + 1 {
-            // overlap or adjacent
-            let first = self.first.min(other.first);
-            let max_last = self.last().max(other.last());
-            let length = NonZeroUsize::new(max_last - first + 1).unwrap();
-            vec![Self { first, length }]
-        } else {
-            // not overlapping or adjacent
-            vec![*self, *other]
+#[derive(Clone, Debug)]
+struct RangeShiftStack(Vec<RangeShift>);
+
+impl RangeShiftStack {
+    /* Each value is moved by the first shift whose source contains it, and values not in any
+     * source are left where they are. */
+    fn apply(&self, val: &IntervalSet<usize>) -> IntervalSet<usize> {
+        let mut remaining = val.clone();
+        let mut out_val: IntervalSet<usize> = IntervalSet::new();
+        for shift in self.0.iter() {
+            if remaining.is_empty() {
+                break;
+            }
+            let res = shift.apply_to_set(&remaining);
+            out_val = out_val.union(&res.image);
+            remaining = res.not_mapped;
         }
+        out_val.union(&remaining)
     }
 
-    fn combine_vector(vals: Vec<Self>) -> Vec<Self> {
-        let mut val_copy = vals.clone();
-        val_copy.sort_by_key(|k| k.first);
-        let mut out_vec: Vec<Self> = vec![];
-        for val in val_copy {
-            if out_vec.is_empty() {
-                out_vec.push(val);
-            } else {
-                let rightmost = out_vec.pop().unwrap();
-                let mut to_push = rightmost.combine(&val);
-                out_vec.append(&mut to_push);
-            }
+    /* The values that `apply` takes into `val`: for each shift, the part of its source not
+     * already covered by an earlier shift that it moves into `val`, plus the part of `val` not in
+     * any source, which stays where it is. */
+    fn invert(&self, val: &IntervalSet<usize>) -> IntervalSet<usize> {
+        let mut covered: IntervalSet<usize> = IntervalSet::new();
+        let mut out_val: IntervalSet<usize> = IntervalSet::new();
+        for shift in self.0.iter() {
+            out_val = out_val.union(&shift.invert(val).difference(&covered));
+            covered = covered.union(&shift.source());
         }
-        out_vec
+        out_val.union(&val.difference(&covered))
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct IntegerIntervalUnion(Vec<IntegerInterval>);
+#[derive(Clone, Debug)]
+struct RangeShiftStackSequence(Vec<RangeShiftStack>);
 
-impl IntegerIntervalUnion {
-    fn simplify(&mut self) {
-        self.0 = IntegerInterval::combine_vector(self.0.clone());
+impl RangeShiftStackSequence {
+    fn apply(&self, val: &IntervalSet<usize>) -> IntervalSet<usize> {
+        let mut out_val = val.clone();
+        for stack in self.0.iter() {
+            out_val = stack.apply(&out_val);
+        }
+        out_val
     }
 
-    fn min(&self) -> Option<usize> {
-        self.0.iter().map(|k| k.first).min()
+    // All the values that end up in `val`
+    fn invert(&self, val: &IntervalSet<usize>) -> IntervalSet<usize> {
+        let mut out_val = val.clone();
+        for stack in self.0.iter().rev() {
+            out_val = stack.invert(&out_val);
+        }
+        out_val
     }
 
-    fn read_seeds(input: String, part2: bool) -> Self {
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        let mut input_numbers: Vec<usize> = parts
-            .iter()
-            .map(|k| usize::from_str(k))
-            .filter(|k| k.is_ok())
-            .map(|k| k.unwrap())
-            .collect();
-        if part2 {
-            let mut ranges: Vec<IntegerInterval> = vec![];
-            while input_numbers.len() > 1 {
-                let length_raw = input_numbers.pop().unwrap();
-                let first = input_numbers.pop().unwrap();
-                if length_raw > 0 {
-                    ranges.push(IntegerInterval {
-                        first,
-                        length: NonZeroUsize::new(length_raw).unwrap(),
-                    });
-                }
-            }
-            let mut out_val = Self(ranges);
-            out_val.simplify();
-            out_val
-        } else {
-            Self(
-                input_numbers
-                    .iter()
-                    .map(|k| IntegerInterval::single(*k))
-                    .collect(),
-            )
-        }
+    // A single map doing the same thing as applying each stack in turn
+    fn compose(&self) -> PiecewiseMap {
+        self.0.iter().fold(PiecewiseMap::identity(), |map, stack| {
+            map.then(&PiecewiseMap::from_stack(stack))
+        })
     }
 }
 
+// Every value in `first..=last` is moved by `offset`
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
-struct RangeShift {
-    source_interval: IntegerInterval,
-    destination_start: usize,
+struct Segment {
+    first: usize,
+    last: usize,
+    offset: i128,
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
-struct RangeShiftResult {
-    image: Option<IntegerInterval>,
-    not_mapped: Vec<IntegerInterval>,
+impl Segment {
+    fn shift(&self, val: usize) -> usize {
+        (val as i128 + self.offset) as usize
+    }
 }
 
-impl RangeShift {
-    fn applies_to_interval(&self, val: &IntegerInterval) -> bool {
-        self.source_interval.overlapping(val)
+/* A map on all of `usize`, stored as sorted segments that cover everything without overlapping.
+ * Neighbouring segments always have different offsets, so two maps that agree everywhere are
+ * stored the same way. */
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct PiecewiseMap(Vec<Segment>);
+
+impl PiecewiseMap {
+    fn identity() -> Self {
+        Self(vec![Segment {
+            first: 0,
+            last: usize::MAX,
+            offset: 0,
+        }])
     }
 
-    fn apply_to_interval(&self, val: &IntegerInterval) -> RangeShiftResult {
-        let not_mapped: Vec<IntegerInterval> = val.set_minus(&self.source_interval);
-        let image: Option<IntegerInterval> =
-            if let Some(overlap) = val.overlap(&self.source_interval) {
-                Some(IntegerInterval {
-                    first: self.destination_start + overlap.first - self.source_interval.first,
-                    length: overlap.length,
-                })
-            } else {
-                None
-            };
-        RangeShiftResult { image, not_mapped }
+    // Sorts the segments and merges neighbours with the same offset
+    fn normalized(mut segments: Vec<Segment>) -> Self {
+        segments.sort_by_key(|k| k.first);
+        let mut out_vec: Vec<Segment> = vec![];
+        for seg in segments {
+            match out_vec.last_mut() {
+                Some(prev) if prev.offset == seg.offset => prev.last = seg.last,
+                _ => out_vec.push(seg),
+            }
+        }
+        Self(out_vec)
     }
 
-    fn read_line(s: String) -> Result<Self, Box<dyn Error>> {
-        let parts: Vec<&str> = s.split_whitespace().collect();
-        let destination_start: usize = parts
-            .get(0)
-            .ok_or(AdventError("malformed input".into()))?
-            .parse()?;
-        let first: usize = parts
-            .get(1)
-            .ok_or(AdventError("malformed input".into()))?
-            .parse()?;
-        let length = NonZeroUsize::new(
-            parts
-                .get(2)
-                .ok_or(AdventError("malformed input".into()))?
-                .parse()?,
-        )
-        .ok_or(AdventError("zero-length range".into()))?;
-        let source_interval = IntegerInterval { first, length };
+    fn from_stack(stack: &RangeShiftStack) -> Self {
+        let mut covered: IntervalSet<usize> = IntervalSet::new();
+        let mut segments: Vec<Segment> = vec![];
+        for shift in stack.0.iter() {
+            let source = shift.source();
+            // Earlier shifts take precedence
+            for range in source.difference(&covered).intervals() {
+                segments.push(Segment {
+                    first: *range.start(),
+                    last: *range.end(),
+                    offset: shift.offset(),
+                });
+            }
+            covered = covered.union(&source);
+        }
+        for range in covered.complement(0..=usize::MAX).intervals() {
+            segments.push(Segment {
+                first: *range.start(),
+                last: *range.end(),
+                offset: 0,
+            });
+        }
+        Self::normalized(segments)
+    }
 
-        Ok(RangeShift {
-            source_interval,
-            destination_start,
-        })
+    // Index of the segment containing `val`
+    fn find(&self, val: usize) -> usize {
+        self.0.partition_point(|k| k.last < val)
+    }
+
+    fn eval(&self, val: usize) -> usize {
+        self.0[self.find(val)].shift(val)
+    }
+
+    // The pieces of `first..=last` lying in each segment, along with the segment
+    fn pieces(&self, first: usize, last: usize) -> impl Iterator<Item = (usize, usize, &Segment)> {
+        self.0[self.find(first)..]
+            .iter()
+            .take_while(move |k| k.first <= last)
+            .map(move |k| (first.max(k.first), last.min(k.last), k))
     }
+
+    // The map that applies `self` and then `other`
+    fn then(&self, other: &Self) -> Self {
+        let mut segments: Vec<Segment> = vec![];
+        for seg in self.0.iter() {
+            for (first, last, next) in other.pieces(seg.shift(seg.first), seg.shift(seg.last)) {
+                segments.push(Segment {
+                    first: (first as i128 - seg.offset) as usize,
+                    last: (last as i128 - seg.offset) as usize,
+                    offset: seg.offset + next.offset,
+                });
+            }
+        }
+        Self::normalized(segments)
+    }
+}
+
+/// The seeds (read as in part 1 or part 2) that end up in one of the `locations`.
+pub fn seeds_reaching(
+    input: Vec<String>,
+    locations: &IntervalSet<usize>,
+    part2: bool,
+) -> Result<IntervalSet<usize>, Box<dyn Error>> {
+    let (seeds, map_stack) = parse_input(input, part2)?;
+    Ok(map_stack.invert(locations).intersection(&seeds))
 }
 
+// A map from one category to another, e.g. "seed-to-soil map:" and the lines after it
 #[derive(Clone, Debug)]
-struct RangeShiftStack(Vec<RangeShift>);
+struct CategoryMap {
+    from: String,
+    to: String,
+    stack: RangeShiftStack,
+}
 
-impl RangeShiftStack {
-    fn apply(&self, int_union: &IntegerIntervalUnion) -> IntegerIntervalUnion {
-        let stack_len: usize = self.0.len();
-        let mut out_vec: Vec<IntegerInterval> = vec![];
-        let mut source_vec: Vec<IntegerInterval> = int_union.0.clone();
-        while !source_vec.is_empty() {
-            let mut pos: usize = 0;
-            let mut matched = false;
-            let next_int = source_vec.pop().unwrap();
-            while !matched && pos < stack_len {
-                if self.0[pos].applies_to_interval(&next_int) {
-                    let mut res = self.0[pos].apply_to_interval(&next_int);
-                    source_vec.append(&mut res.not_mapped);
-                    out_vec.push(res.image.unwrap());
-                    matched = true;
-                } else {
-                    pos += 1;
+// e.g. "seed-to-soil map:"
+fn read_header(line: &str) -> Result<(String, String), Box<dyn Error>> {
+    Ok(parse_all(
+        terminated(
+            pair(token(word), preceded(literal("-to-"), word)),
+            token(literal("map:")),
+        ),
+        line,
+    )?)
+}
+
+#[derive(Clone, Debug)]
+struct Almanac {
+    seeds: IntervalSet<usize>,
+    maps: Vec<CategoryMap>,
+}
+
+impl Almanac {
+    fn parse(input: Vec<String>, part2: bool) -> Result<Self, Box<dyn Error>> {
+        let mut lines = input.iter();
+        let seeds = match lines.next() {
+            Some(line) => read_seeds(line, part2)?,
+            None => return Err(Box::new(AdventError("empty almanac".into()))),
+        };
+        let mut maps: Vec<CategoryMap> = vec![];
+        for line in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Ok((from, to)) = read_header(line) {
+                maps.push(CategoryMap {
+                    from,
+                    to,
+                    stack: RangeShiftStack(vec![]),
+                });
+            } else {
+                let shift = RangeShift::read_line(line.to_string()).map_err(|e| {
+                    AdventError(format!(
+                        "\"{}\" is neither a map header nor a range ({})",
+                        line, e
+                    ))
+                })?;
+                match maps.last_mut() {
+                    Some(map) => map.stack.0.push(shift),
+                    None => {
+                        return Err(Box::new(AdventError(format!(
+                            "\"{}\" comes before any map header",
+                            line
+                        ))))
+                    }
                 }
             }
-            if !matched {
-                out_vec.push(next_int);
+        }
+        Ok(Self { seeds, maps })
+    }
+
+    /* The maps in the order they have to be applied. Each category must be mapped to at most one
+     * other category and reached from at most one other category, and following the maps from
+     * the category that isn't reached from anywhere must use all of them. */
+    fn chain(&self) -> Result<Vec<&CategoryMap>, Box<dyn Error>> {
+        let mut next: HashMap<&str, &CategoryMap> = HashMap::new();
+        let mut reached: HashSet<&str> = HashSet::new();
+        for map in self.maps.iter() {
+            if let Some(other) = next.insert(&map.from, map) {
+                return Err(Box::new(AdventError(format!(
+                    "branch: {} is mapped to both {} and {}",
+                    map.from, other.to, map.to
+                ))));
             }
+            if !reached.insert(&map.to) {
+                return Err(Box::new(AdventError(format!(
+                    "branch: {} is reached from more than one category",
+                    map.to
+                ))));
+            }
+        }
+        // The first start in the file begins the chain
+        let starts: Vec<&str> = self
+            .maps
+            .iter()
+            .map(|k| k.from.as_str())
+            .filter(|k| !reached.contains(k))
+            .collect();
+        if starts.len() > 1 {
+            return Err(Box::new(AdventError(format!(
+                "missing link: nothing leads to {}",
+                starts[1..].join(", ")
+            ))));
+        }
+        let mut out_vec: Vec<&CategoryMap> = vec![];
+        let mut current = starts.first().copied();
+        while let Some(map) = current.and_then(|k| next.get(k)) {
+            out_vec.push(map);
+            current = Some(&map.to);
+        }
+        if out_vec.len() < self.maps.len() {
+            Err(Box::new(AdventError("the maps form a cycle".into())))
+        } else {
+            Ok(out_vec)
+        }
+    }
+
+    // The maps taking category `from` to category `to`
+    fn sequence(&self, from: &str, to: &str) -> Result<RangeShiftStackSequence, Box<dyn Error>> {
+        let chain = self.chain()?;
+        let categories = chain_categories(&chain);
+        let position = |name: &str| {
+            categories
+                .iter()
+                .position(|k| *k == name)
+                .ok_or(AdventError(format!("unknown category {}", name)))
+        };
+        let (first, last) = (position(from)?, position(to)?);
+        if first > last {
+            return Err(Box::new(AdventError(format!(
+                "no chain of maps from {} to {}",
+                from, to
+            ))));
         }
-        let mut ret_val = IntegerIntervalUnion(out_vec);
-        ret_val.simplify();
-        ret_val
+        Ok(RangeShiftStackSequence(
+            chain[first..last].iter().map(|k| k.stack.clone()).collect(),
+        ))
+    }
+
+    // The chain of maps, ready to trace values through
+    fn tracer(&self) -> Result<Tracer<'_>, Box<dyn Error>> {
+        let chain = self.chain()?;
+        Ok(Tracer {
+            categories: chain_categories(&chain),
+            maps: chain
+                .iter()
+                .map(|k| PiecewiseMap::from_stack(&k.stack))
+                .collect(),
+        })
     }
 }
 
-#[derive(Clone, Debug)]
-struct RangeShiftStackSequence(Vec<RangeShiftStack>);
+// The categories of a chain of maps in order (e.g. ["seed", "soil", ..., "location"]), and the
+// maps between them
+struct Tracer<'a> {
+    categories: Vec<&'a str>,
+    maps: Vec<PiecewiseMap>,
+}
 
-impl RangeShiftStackSequence {
-    fn apply(&self, val: &IntegerIntervalUnion) -> IntegerIntervalUnion {
-        let mut out_val = val.clone();
-        for stack in self.0.clone() {
-            out_val = stack.apply(&out_val);
+impl Tracer<'_> {
+    // The value in each category that `val` (in the first category) corresponds to
+    fn trace(&self, val: usize) -> Vec<(&str, usize)> {
+        let mut val = val;
+        let mut values: Vec<usize> = vec![val];
+        for map in self.maps.iter() {
+            val = map.eval(val);
+            values.push(val);
         }
-        out_val
+        self.categories.iter().copied().zip(values).collect()
     }
 }
 
+// The categories a chain of maps goes through, in order
+fn chain_categories<'a>(chain: &[&'a CategoryMap]) -> Vec<&'a str> {
+    chain
+        .first()
+        .map(|k| k.from.as_str())
+        .into_iter()
+        .chain(chain.iter().map(|k| k.to.as_str()))
+        .collect()
+}
+
 fn parse_input(
     input: Vec<String>,
     part2: bool,
-) -> Result<(IntegerIntervalUnion, RangeShiftStackSequence), Box<dyn Error>> {
-    let mut seeds = IntegerIntervalUnion(vec![]);
-    let mut map_stack = RangeShiftStackSequence(vec![]);
-    let mut current_rss = RangeShiftStack(vec![]);
-    let mut ignore_next = true;
-    for (idx, line) in input.iter().enumerate() {
-        if idx == 0 {
-            let parts: Vec<String> = line.split(':').map(|k| k.trim().into()).collect();
-            seeds = IntegerIntervalUnion::read_seeds(
-                parts
-                    .get(1)
-                    .ok_or(AdventError("malformed input (seeds)".into()))?
-                    .into(),
-                part2,
-            );
-        } else if idx == 1 {
-            /* pass */
-        } else {
-            if ignore_next {
-                ignore_next = false;
-            } else if line.trim().is_empty() {
-                map_stack.0.push(current_rss);
-                current_rss = RangeShiftStack(vec![]);
-            } else {
-                if let Ok(shift) = RangeShift::read_line(line.to_string()) {
-                    current_rss.0.push(shift);
-                }
-            }
-        }
+) -> Result<(IntervalSet<usize>, RangeShiftStackSequence), Box<dyn Error>> {
+    let almanac = Almanac::parse(input, part2)?;
+    let sequence = RangeShiftStackSequence(
+        almanac
+            .chain()?
+            .into_iter()
+            .map(|k| k.stack.clone())
+            .collect(),
+    );
+    Ok((almanac.seeds, sequence))
+}
+
+/// Converts `val` from category `from` to category `to` (e.g. from "soil" to "humidity").
+pub fn convert(
+    input: Vec<String>,
+    from: &str,
+    to: &str,
+    val: usize,
+) -> Result<usize, Box<dyn Error>> {
+    Ok(Almanac::parse(input, false)?
+        .sequence(from, to)?
+        .compose()
+        .eval(val))
+}
+
+// e.g. "Seed 79, soil 81, ..., location 82."
+fn describe_trace(trace: Vec<(&str, usize)>) -> String {
+    let steps: Vec<String> = trace
+        .into_iter()
+        .map(|(category, val)| format!("{} {}", category, val))
+        .collect();
+    let mut out_val = steps.join(", ");
+    if let Some(k) = out_val.get_mut(0..1) {
+        k.make_ascii_uppercase();
     }
-    if !current_rss.0.is_empty() {
-        map_stack.0.push(current_rss);
+    format!("{}.", out_val)
+}
+
+/// Describes where `seed` goes in each category, e.g. "Seed 79, soil 81, ..., location 82."
+pub fn seed_chain(input: Vec<String>, seed: usize) -> Result<String, Box<dyn Error>> {
+    Ok(describe_trace(
+        Almanac::parse(input, false)?.tracer()?.trace(seed),
+    ))
+}
+
+// The category chain, then where each part 1 seed goes in it, one per line
+fn explanation(input: Vec<String>) -> Result<String, Box<dyn Error>> {
+    let almanac = Almanac::parse(input, false)?;
+    let tracer = almanac.tracer()?;
+    let mut lines: Vec<String> = vec![tracer.categories.join(" -> ")];
+    lines.extend(
+        almanac
+            .seeds
+            .iter()
+            .map(|seed| describe_trace(tracer.trace(seed))),
+    );
+    Ok(lines.join("\n"))
+}
+
+/// Prints the category chain and where each part 1 seed goes in it.
+pub fn run_explain() {
+    match explanation(common::get_day(5).unwrap()) {
+        Ok(k) => println!("{}", k),
+        Err(e) => println!("Invalid almanac: {}", e),
     }
-    Ok((seeds, map_stack))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::common;
+    use crate::common::rng::Lcg;
 
     const EXAMPLE_INPUT: &str = "seeds: 79 14 55 13
 
@@ -380,21 +629,22 @@ humidity-to-location map:
     }
 
     #[test]
-    fn overlap_test1() {
-        let interval = IntegerInterval {
-            first: 50,
-            length: NonZeroUsize::new(48).unwrap(),
-        };
-        let val = IntegerInterval::single(79);
-        assert_eq!(interval.overlap(&val), Some(val));
+    fn seed_range_overflow_test() {
+        let line = format!("seeds: 1 2 {} 2", usize::MAX);
+        assert!(read_seeds(&line, true).is_err());
+        let line = format!("seeds: 1 2 {} 1", usize::MAX);
+        assert_eq!(
+            read_seeds(&line, true).unwrap(),
+            IntervalSet::from_ranges(vec![1..=2, usize::MAX..=usize::MAX])
+        );
     }
 
     #[test]
     fn range_shift_test1() {
         let rs = RangeShift::read_line("52 50 48".into()).unwrap();
-        let val = IntegerInterval::single(79);
-        let output = rs.apply_to_interval(&val);
-        assert_eq!(output.image, Some(IntegerInterval::single(81)));
+        let val = IntervalSet::single(79);
+        let output = rs.apply_to_set(&val);
+        assert_eq!(output.image, IntervalSet::single(81));
         assert!(output.not_mapped.is_empty());
     }
 
@@ -404,8 +654,249 @@ humidity-to-location map:
             RangeShift::read_line("50 98 2".into()).unwrap(),
             RangeShift::read_line("52 50 48".into()).unwrap(),
         ]);
-        let output = rss.apply(&IntegerIntervalUnion(vec![IntegerInterval::single(79)]));
-        assert_eq!(output.0.len(), 1);
-        assert_eq!(output.0[0], IntegerInterval::single(81));
+        let output = rss.apply(&IntervalSet::single(79));
+        assert_eq!(output.num_intervals(), 1);
+        assert_eq!(output, IntervalSet::single(81));
+    }
+
+    #[test]
+    fn range_shift_test3() {
+        let rs = RangeShift::read_line("0 15 37".into()).unwrap();
+        let val = IntervalSet::from_range(10..=60);
+        let output = rs.apply_to_set(&val);
+        assert_eq!(output.image, IntervalSet::from_range(0..=36));
+        assert_eq!(
+            output.not_mapped,
+            IntervalSet::from_ranges(vec![10..=14, 52..=60])
+        );
+    }
+
+    #[test]
+    fn invert_test() {
+        let example_input = common::split_string(EXAMPLE_INPUT.into());
+        let (_, map_stack) = parse_input(example_input, false).unwrap();
+        for locations in [
+            IntervalSet::from_range(0..=50),
+            IntervalSet::from_ranges(vec![10..=20, 60..=70, 95..=200]),
+            IntervalSet::single(46),
+        ] {
+            let seeds = map_stack.invert(&locations);
+            for x in 0..=150 {
+                assert_eq!(
+                    seeds.contains(x),
+                    map_stack
+                        .apply(&IntervalSet::single(x))
+                        .is_subset(&locations),
+                    "seed {}",
+                    x
+                );
+            }
+            assert!(map_stack.apply(&seeds).is_subset(&locations));
+        }
+        // The identity regions go through unchanged
+        let stack = &map_stack.0[0];
+        assert_eq!(
+            stack.invert(&IntervalSet::from_range(0..=10)),
+            IntervalSet::from_range(0..=10)
+        );
+        assert_eq!(
+            stack.invert(&IntervalSet::from_range(0..=51)),
+            IntervalSet::from_ranges(vec![0..=49, 98..=99])
+        );
+    }
+
+    #[test]
+    fn seeds_reaching_test() {
+        let example_input = common::split_string(EXAMPLE_INPUT.into());
+        // Seeds 79, 14, 55 and 13 go to locations 82, 43, 86 and 35
+        assert_eq!(
+            seeds_reaching(
+                example_input.clone(),
+                &IntervalSet::from_range(0..=50),
+                false
+            )
+            .unwrap(),
+            IntervalSet::from_ranges(vec![13..=14])
+        );
+        let seeds = seeds_reaching(example_input, &IntervalSet::single(46), true).unwrap();
+        assert_eq!(seeds, IntervalSet::single(82));
+    }
+
+    // Pseudo-random stacks of shifts with overlapping sources, all below 1000
+    fn generated_sequence(num_stacks: usize) -> RangeShiftStackSequence {
+        let mut rng = Lcg::new(0x2023_1205);
+        let mut next = |bound: u64| rng.below(bound) as usize;
+        RangeShiftStackSequence(
+            (0..num_stacks)
+                .map(|_| {
+                    let num_shifts = next(6);
+                    RangeShiftStack(
+                        (0..num_shifts)
+                            .map(|_| {
+                                let source_first = next(500);
+                                RangeShift {
+                                    source_first,
+                                    source_last: source_first + next(200),
+                                    destination_start: next(500),
+                                }
+                            })
+                            .collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn assert_normalized(map: &PiecewiseMap) {
+        assert_eq!(map.0.first().unwrap().first, 0);
+        assert_eq!(map.0.last().unwrap().last, usize::MAX);
+        for k in map.0.windows(2) {
+            assert!(k[0].first <= k[0].last);
+            assert_eq!(k[0].last + 1, k[1].first);
+            assert_ne!(k[0].offset, k[1].offset);
+        }
+    }
+
+    #[test]
+    fn compose_example_test() {
+        let example_input = common::split_string(EXAMPLE_INPUT.into());
+        let (seeds, map_stack) = parse_input(example_input, true).unwrap();
+        let map = map_stack.compose();
+        assert_normalized(&map);
+        for (seed, location) in [(79, 82), (14, 43), (55, 86), (13, 35), (82, 46)] {
+            assert_eq!(map.eval(seed), location);
+        }
+        for seed in seeds.iter() {
+            assert_eq!(
+                IntervalSet::single(map.eval(seed)),
+                map_stack.apply(&IntervalSet::single(seed))
+            );
+        }
+        // Values past all the maps are left alone
+        assert_eq!(map.eval(usize::MAX), usize::MAX);
+    }
+
+    #[test]
+    fn compose_generated_test() {
+        let map_stack = generated_sequence(7);
+        let map = map_stack.compose();
+        assert_normalized(&map);
+        for x in 0..=1200 {
+            assert_eq!(
+                IntervalSet::single(map.eval(x)),
+                map_stack.apply(&IntervalSet::single(x)),
+                "value {}",
+                x
+            );
+        }
+        for stack in map_stack.0.iter() {
+            assert_normalized(&PiecewiseMap::from_stack(stack));
+        }
+    }
+
+    fn example_with_maps(maps: &[usize]) -> Vec<String> {
+        let blocks: Vec<&str> = EXAMPLE_INPUT.split("\n\n").collect();
+        let mut out_vec: Vec<&str> = vec![blocks[0]];
+        out_vec.extend(maps.iter().map(|k| blocks[k + 1]));
+        common::split_string(out_vec.join("\n\n"))
+    }
+
+    #[test]
+    fn header_test() {
+        assert_eq!(
+            read_header("seed-to-soil map:").unwrap(),
+            ("seed".to_string(), "soil".to_string())
+        );
+        assert!(read_header("seed-to- map:").is_err());
+        assert!(read_header("50 98 2").is_err());
+    }
+
+    #[test]
+    fn category_chain_test() {
+        let example_input = common::split_string(EXAMPLE_INPUT.into());
+        let almanac = Almanac::parse(example_input, false).unwrap();
+        assert_eq!(
+            almanac.tracer().unwrap().categories,
+            vec![
+                "seed",
+                "soil",
+                "fertilizer",
+                "water",
+                "light",
+                "temperature",
+                "humidity",
+                "location"
+            ]
+        );
+        // The maps don't have to be in order
+        let shuffled = example_with_maps(&[3, 0, 6, 2, 5, 1, 4]);
+        assert_eq!(part_1(shuffled.clone()).unwrap(), 35);
+        assert_eq!(part_2(shuffled).unwrap(), 46);
+    }
+
+    #[test]
+    fn category_chain_error_test() {
+        // soil-to-fertilizer is missing
+        let missing = example_with_maps(&[0, 2, 3, 4, 5, 6]);
+        let e = Almanac::parse(missing, false).unwrap().chain().unwrap_err();
+        assert_eq!(e.to_string(), "missing link: nothing leads to fertilizer");
+        let mut branch = common::split_string(EXAMPLE_INPUT.into());
+        branch.extend(["".to_string(), "soil-to-water map:".to_string()]);
+        let e = Almanac::parse(branch, false).unwrap().chain().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "branch: soil is mapped to both fertilizer and water"
+        );
+        let mut cycle = common::split_string(EXAMPLE_INPUT.into());
+        cycle.extend(["".to_string(), "location-to-seed map:".to_string()]);
+        let e = Almanac::parse(cycle, false).unwrap().chain().unwrap_err();
+        assert_eq!(e.to_string(), "the maps form a cycle");
+        let orphan = common::split_string("seeds: 1\n1 2 3".into());
+        assert!(Almanac::parse(orphan, false).is_err());
+        // A misspelled header isn't a range either, so its ranges don't end up in the map before
+        let misspelled =
+            EXAMPLE_INPUT.replace("soil-to-fertilizer map:", "soil-to-fertilizer mpa:");
+        let e = Almanac::parse(common::split_string(misspelled), false).unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("\"soil-to-fertilizer mpa:\" is neither a map header nor a range"));
+    }
+
+    #[test]
+    fn convert_test() {
+        let example_input = common::split_string(EXAMPLE_INPUT.into());
+        // Seed 79 is soil 81 and humidity 78; seed 14 is soil 14 and humidity 43
+        assert_eq!(
+            convert(example_input.clone(), "soil", "humidity", 81).unwrap(),
+            78
+        );
+        assert_eq!(
+            convert(example_input.clone(), "soil", "humidity", 14).unwrap(),
+            43
+        );
+        assert_eq!(
+            convert(example_input.clone(), "water", "water", 5).unwrap(),
+            5
+        );
+        assert!(convert(example_input.clone(), "humidity", "soil", 5).is_err());
+        assert!(convert(example_input, "soil", "mud", 5).is_err());
+    }
+
+    #[test]
+    fn seed_chain_test() {
+        let example_input = common::split_string(EXAMPLE_INPUT.into());
+        assert_eq!(
+            seed_chain(example_input.clone(), 79).unwrap(),
+            "Seed 79, soil 81, fertilizer 81, water 81, light 74, temperature 78, humidity 78, location 82."
+        );
+        let explained = explanation(example_input).unwrap();
+        let lines: Vec<&str> = explained.lines().collect();
+        assert_eq!(
+            lines[0],
+            "seed -> soil -> fertilizer -> water -> light -> temperature -> humidity -> location"
+        );
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[4], "Seed 79, soil 81, fertilizer 81, water 81, light 74, temperature 78, humidity 78, location 82.");
+        assert!(explanation(vec!["seeds: 1".into(), "1 2 3".into()]).is_err());
     }
 }
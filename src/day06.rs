@@ -6,10 +6,12 @@
 use std::cmp::Ordering;
 use std::error::Error;
 use std::iter;
-use std::str::FromStr;
 
 use crate::common;
+use crate::common::checked;
+use crate::common::parse::{many, parse_all, section, token, uint};
 use crate::common::AdventError;
+use crate::common::{BigInt, BigUint};
 
 const DAY: usize = 6;
 const PART_2_IMPL: bool = true;
@@ -18,22 +20,40 @@ pub fn run() {
     let input = common::get_day(DAY).unwrap();
     println!(
         "{}",
-        common::soln_output(DAY, 1, part_1(input.clone()).unwrap())
+        common::soln_output(DAY, 1, part_1_big(input.clone()).unwrap())
     );
     if PART_2_IMPL {
-        println!("{}", common::soln_output(DAY, 2, part_2(input).unwrap()));
+        println!(
+            "{}",
+            common::soln_output(DAY, 2, part_2_big(input).unwrap())
+        );
     }
 }
 
 pub fn part_1(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 1);
     Ok(RaceCollection::parse_input(input)?.ways_to_win())
 }
 
 pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 2);
     let rekerned_input: Vec<String> = input.iter().map(|k| rekern_line(k.into())).collect();
     Ok(RaceCollection::parse_input(rekerned_input)?.ways_to_win())
 }
 
+/* Same as part_1 and part_2, but fall back to arbitrary precision if the answer doesn't fit in a
+ * u64 */
+pub fn part_1_big(input: Vec<String>) -> Result<BigUint, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 1);
+    Ok(RaceCollection::parse_input(input)?.ways_to_win_big())
+}
+
+pub fn part_2_big(input: Vec<String>) -> Result<BigUint, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 2);
+    let rekerned_input: Vec<String> = input.iter().map(|k| rekern_line(k.into())).collect();
+    Ok(RaceCollection::parse_input(rekerned_input)?.ways_to_win_big())
+}
+
 fn rekern_line(s: String) -> String {
     let parts: Vec<String> = s.split(':').map(|k| k.to_string()).collect();
     if parts.len() < 2 {
@@ -50,28 +70,43 @@ struct Race {
 }
 
 impl Race {
-    fn distance(&self, button_held: u64) -> u64 {
+    // A distance too big for a u64 certainly beats the record
+    fn beats_record(&self, button_held: u64) -> bool {
         if button_held <= self.duration {
-            button_held * (self.duration - button_held)
+            match button_held.checked_mul(self.duration - button_held) {
+                Some(d) => d > self.record,
+                None => true,
+            }
         } else {
-            0
+            false
         }
     }
 
-    /* We will beat the record for values of t such that self.distance(t) > self.record.
+    /* We will beat the record for values of t such that t * (self.duration - t) > self.record.
      * To determine how many values there are, we first solve the quadratic equation
-     * self.distance(t) == self.record. If there are two solutions, any integers strictly between
+     * t * (self.duration - t) == self.record. If there are two solutions, any integers strictly between
      * these are values that beat the record.
      */
 
-    // Calculate the discriminant of the quadratic equation
-    fn desc_i(&self) -> i128 {
-        (self.duration.pow(2) as i128) - 4 * (self.record as i128)
+    // Calculate the discriminant of the quadratic equation (which can overflow an i128 for long
+    // races)
+    fn desc_i(&self) -> BigInt {
+        let d = self.duration as i128;
+        match d
+            .checked_mul(d)
+            .and_then(|k| k.checked_sub(4 * (self.record as i128)))
+        {
+            Some(k) => BigInt::from(k),
+            None => {
+                &BigInt::from(self.duration).pow(2)
+                    - &(&BigInt::from(4_u64) * &BigInt::from(self.record))
+            }
+        }
     }
 
     fn raw_zeros(&self) -> Option<(f64, f64)> {
         let desc_i = self.desc_i();
-        match desc_i.cmp(&0) {
+        match desc_i.signum() {
             Ordering::Less => None,
             Ordering::Equal => {
                 let d = self.duration as f64;
@@ -79,30 +114,30 @@ impl Race {
             }
             Ordering::Greater => {
                 let d = self.duration as f64;
-                let sr = (desc_i as f64).sqrt();
+                let sr = desc_i.to_f64().sqrt();
                 Some(((d - sr) / 2.0, (d + sr) / 2.0))
             }
         }
     }
 
     fn winning_range(&self) -> Option<(u64, u64)> {
-        if self.desc_i() > 0 {
+        if self.desc_i().signum() == Ordering::Greater {
             let (lower_root, upper_root) = self.raw_zeros().unwrap();
             // find lowest winning integer (being carefule because casting to int can cause small
             // errors)
             let lower_root_floor = lower_root.floor() as u64;
-            let mut lower = if lower_root_floor > 1 {
-                lower_root_floor - 1
-            } else {
-                0
-            };
-            while self.distance(lower) <= self.record {
+            let upper_limit = (upper_root.ceil() as u64).saturating_add(1);
+            let mut lower = lower_root_floor.saturating_sub(1);
+            while !self.beats_record(lower) {
+                // no integer strictly between the roots, e.g. roots 2 and 3
+                if lower >= upper_limit {
+                    return None;
+                }
                 lower += 1;
             }
             // find highest winning integer
-            let upper_root_ceiling = upper_root.ceil() as u64;
-            let mut upper = upper_root_ceiling + 1;
-            while self.distance(upper) <= self.record {
+            let mut upper = upper_limit;
+            while upper > lower && !self.beats_record(upper) {
                 upper -= 1;
             }
             Some((lower, upper))
@@ -113,7 +148,7 @@ impl Race {
 
     fn ways_to_win(&self) -> u64 {
         match self.winning_range() {
-            Some((lower, upper)) => upper - lower + 1,
+            Some((lower, upper)) => checked::add(upper - lower, 1, "ways_to_win"),
             None => 0,
         }
     }
@@ -123,31 +158,28 @@ struct RaceCollection(Vec<Race>);
 
 impl RaceCollection {
     fn ways_to_win(&self) -> u64 {
-        self.0.iter().map(|k| k.ways_to_win()).product()
+        checked::product(
+            self.0.iter().map(|k| k.ways_to_win()),
+            "product of ways to win",
+        )
+    }
+
+    fn ways_to_win_big(&self) -> BigUint {
+        let ways: Vec<u64> = self.0.iter().map(|k| k.ways_to_win()).collect();
+        match ways.iter().try_fold(1_u64, |acc, k| acc.checked_mul(*k)) {
+            Some(k) => BigUint::from(k),
+            None => ways.into_iter().map(BigUint::from).product(),
+        }
     }
 
     fn parse_input(input: Vec<String>) -> Result<Self, Box<dyn Error>> {
         if input.len() < 2 {
             Err(Box::new(AdventError("input has too few lines".into())))
         } else {
-            let durations: Vec<u64> = input[0]
-                .strip_prefix("Time:")
-                .ok_or(AdventError("Time line of input malformed".into()))?
-                .trim()
-                .split_whitespace()
-                .map(|k| u64::from_str(k))
-                .filter(|k| k.is_ok())
-                .map(|k| k.unwrap())
-                .collect();
-            let records: Vec<u64> = input[1]
-                .strip_prefix("Distance:")
-                .ok_or(AdventError("Distance line of input malformed".into()))?
-                .trim()
-                .split_whitespace()
-                .map(|k| u64::from_str(k))
-                .filter(|k| k.is_ok())
-                .map(|k| k.unwrap())
-                .collect();
+            let durations: Vec<u64> =
+                parse_all(section("Time", many(token(uint::<u64>))), &input[0])?;
+            let records: Vec<u64> =
+                parse_all(section("Distance", many(token(uint::<u64>))), &input[1])?;
             if records.len() != durations.len() {
                 Err(Box::new(AdventError(
                     "malformed input: # of times != # of distances".into(),
@@ -179,6 +211,45 @@ Distance:  9  40  200";
         assert_eq!(r.winning_range().unwrap(), (2, 5));
     }
 
+    #[test]
+    fn winning_range_no_integer_test() {
+        // The roots are 2 and 3, and tying the record doesn't win
+        let r = Race {
+            duration: 5,
+            record: 6,
+        };
+        assert_eq!(r.winning_range(), None);
+        assert_eq!(r.ways_to_win(), 0);
+    }
+
+    #[test]
+    fn winning_range_long_race_test() {
+        // The distances and the discriminant here don't fit in a u64
+        let r = Race {
+            duration: 10_000_000_000,
+            record: 9_000_000_000_000_000_000,
+        };
+        assert_eq!(r.ways_to_win(), 7_999_999_999);
+    }
+
+    #[test]
+    fn part1_big_test() {
+        let example_input = common::split_string(EXAMPLE_INPUT.into());
+        assert_eq!(part_1_big(example_input).unwrap(), BigUint::from(288_u64));
+        let races = RaceCollection(
+            (0..3)
+                .map(|_| Race {
+                    duration: 10_000_000_000,
+                    record: 9_000_000_000_000_000_000,
+                })
+                .collect(),
+        );
+        assert_eq!(
+            races.ways_to_win_big(),
+            BigUint::from(7_999_999_999_u64).pow(3)
+        );
+    }
+
     #[test]
     fn part1_test() {
         let example_input = common::split_string(EXAMPLE_INPUT.into());
@@ -9,6 +9,7 @@ use std::error::Error;
 use std::str::FromStr;
 
 use crate::common;
+use crate::common::checked;
 use crate::common::AdventError;
 
 const DAY: usize = 7;
@@ -32,10 +33,12 @@ pub fn run() {
 }
 
 pub fn part_1(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 1);
     Ok(value(parse_input(input)))
 }
 
 pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 2);
     Ok(value2(parse_input_2(input)))
 }
 
@@ -178,7 +181,7 @@ impl FromStr for Hand {
             Err(AdventError::new("hands consist of exactly five cards"))
         } else {
             let mut out_val: [CamelCard; 5] = [CamelCard::Ace; 5];
-            for (idx, val) in s.chars().map(|c| CamelCard::try_from(c)).enumerate() {
+            for (idx, val) in s.chars().map(CamelCard::try_from).enumerate() {
                 match val {
                     Ok(card) => {
                         out_val[idx] = card;
@@ -206,7 +209,7 @@ impl Hand {
         let mut out_vec: Vec<usize> = self
             .full_freq()
             .iter()
-            .map(|k| *k)
+            .copied()
             .filter(|k| *k != 0)
             .collect();
         out_vec.sort();
@@ -214,14 +217,14 @@ impl Hand {
     }
 
     fn hand_type(&self) -> HandType {
-        match self.hand_shape().as_slice() {
-            &[5] => HandType::FiveOfAKind,
-            &[1, 4] => HandType::FourOfAKind,
-            &[2, 3] => HandType::FullHouse,
-            &[1, 1, 3] => HandType::ThreeOfAKind,
-            &[1, 2, 2] => HandType::TwoPair,
-            &[1, 1, 1, 2] => HandType::OnePair,
-            &[1, 1, 1, 1, 1] => HandType::HighCard,
+        match *self.hand_shape().as_slice() {
+            [5] => HandType::FiveOfAKind,
+            [1, 4] => HandType::FourOfAKind,
+            [2, 3] => HandType::FullHouse,
+            [1, 1, 3] => HandType::ThreeOfAKind,
+            [1, 2, 2] => HandType::TwoPair,
+            [1, 1, 1, 2] => HandType::OnePair,
+            [1, 1, 1, 1, 1] => HandType::HighCard,
             _ => {
                 panic!("This shouldn't happen!");
             }
@@ -234,7 +237,7 @@ impl Ord for Hand {
         match self.hand_type().cmp(&other.hand_type()) {
             Ordering::Equal => {
                 for (self_card, other_card) in self.0.iter().zip(other.0.iter()) {
-                    match self_card.cmp(&other_card) {
+                    match self_card.cmp(other_card) {
                         Ordering::Equal => { /* pass */ }
                         k => {
                             return k;
@@ -296,10 +299,8 @@ fn parse_input(input: Vec<String>) -> Vec<(Hand, u64)> {
     for line in input {
         let parts: Vec<&str> = line.split_whitespace().collect();
         if parts.len() >= 2 {
-            let hand = Hand::from_str(parts[0]);
-            let bid = u64::from_str(&parts[1]);
-            if hand.is_ok() && bid.is_ok() {
-                out_vec.push((hand.unwrap(), bid.unwrap()));
+            if let (Ok(hand), Ok(bid)) = (Hand::from_str(parts[0]), u64::from_str(parts[1])) {
+                out_vec.push((hand, bid));
             }
         }
     }
@@ -316,21 +317,25 @@ fn parse_input_2(input: Vec<String>) -> Vec<(Hand2, u64)> {
 fn value(hands: Vec<(Hand, u64)>) -> u64 {
     let mut s_hands = hands.clone();
     s_hands.sort_by_key(|k| k.0);
-    s_hands
-        .iter()
-        .enumerate()
-        .map(|(idx, (_, bid))| bid * (idx as u64 + 1))
-        .sum()
+    checked::sum(
+        s_hands
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, bid))| checked::mul(*bid, idx as u64 + 1, "winnings")),
+        "total winnings",
+    )
 }
 
 fn value2(hands: Vec<(Hand2, u64)>) -> u64 {
     let mut s_hands = hands.clone();
     s_hands.sort_by_key(|k| k.0);
-    s_hands
-        .iter()
-        .enumerate()
-        .map(|(idx, (_, bid))| bid * (idx as u64 + 1))
-        .sum()
+    checked::sum(
+        s_hands
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, bid))| checked::mul(*bid, idx as u64 + 1, "winnings")),
+        "total winnings",
+    )
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -379,7 +384,7 @@ impl Hand2 {
             .map(|(c, v)| (*c, *v))
             .collect();
         pairs.sort_by_key(|(_, v)| *v);
-        pairs.iter().map(|(c, _)| *c).last()
+        pairs.iter().map(|(c, _)| *c).next_back()
     }
 
     fn replace_jokers(&self) -> Hand {
@@ -412,7 +417,7 @@ impl Ord for Hand2 {
         match self.hand_type().cmp(&other.hand_type()) {
             Ordering::Equal => {
                 for (self_card, other_card) in self.0.iter().zip(other.0.iter()) {
-                    match self_card.cmp(&other_card) {
+                    match self_card.cmp(other_card) {
                         Ordering::Equal => { /* pass */ }
                         k => {
                             return k;
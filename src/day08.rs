@@ -7,7 +7,12 @@ use std::collections::HashMap;
 use std::error::Error;
 
 use crate::common;
+use crate::common::checked;
+use crate::common::parse::{
+    delimited, literal, map, pair, parse_all, preceded, token, word, Parser,
+};
 use crate::common::AdventError;
+use crate::common::BigUint;
 
 const DAY: usize = 8;
 const PART_1_IMPL: bool = true;
@@ -22,7 +27,10 @@ pub fn run() {
         );
     }
     if PART_2_IMPL {
-        println!("{}", common::soln_output(DAY, 2, part_2(input).unwrap()));
+        println!(
+            "{}",
+            common::soln_output(DAY, 2, part_2_big(input).unwrap())
+        );
     }
     if !(PART_1_IMPL || PART_2_IMPL) {
         println!("Not implemented yet");
@@ -30,13 +38,21 @@ pub fn run() {
 }
 
 pub fn part_1(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 1);
     let all_data = AllData::parse(input);
     let start = all_data.get_start1()?;
     all_data.num_moves(start, false)
 }
 
 pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
-    Ok(AllData::parse(input).num_moves_2()?)
+    let _part = checked::solving(DAY, 2);
+    AllData::parse(input).num_moves_2()
+}
+
+/* Same as part_2, but falls back to arbitrary precision if the answer doesn't fit in a u64 */
+pub fn part_2_big(input: Vec<String>) -> Result<BigUint, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 2);
+    Ok(lcm_all_big(&AllData::parse(input).cycle_lengths()?))
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -59,11 +75,7 @@ impl TryFrom<char> for Direction {
 
 impl Direction {
     fn parse_seq(s: &str) -> Vec<Self> {
-        s.chars()
-            .map(|c| Direction::try_from(c))
-            .filter(|k| k.is_ok())
-            .map(|k| k.unwrap())
-            .collect()
+        s.chars().flat_map(Direction::try_from).collect()
     }
 }
 
@@ -75,6 +87,24 @@ struct Location {
 }
 
 impl Location {
+    // e.g. "AAA = (BBB, CCC)"
+    fn parser() -> impl Parser<Self> {
+        map(
+            pair(
+                token(word),
+                preceded(
+                    token(literal("=")),
+                    delimited(
+                        token(literal("(")),
+                        pair(token(word), preceded(token(literal(",")), token(word))),
+                        token(literal(")")),
+                    ),
+                ),
+            ),
+            |(name, (left, right))| Self { name, left, right },
+        )
+    }
+
     fn get_key(&self, direction: Direction) -> String {
         match direction {
             Direction::Left => self.left.clone(),
@@ -84,17 +114,17 @@ impl Location {
 
     fn is_start(&self, part2: bool) -> bool {
         if part2 {
-            self.name.chars().last().unwrap() == 'A'
+            self.name.ends_with('A')
         } else {
-            self.name == "AAA".to_string()
+            self.name == "AAA"
         }
     }
 
     fn is_end(&self, part2: bool) -> bool {
         if part2 {
-            self.name.chars().last().unwrap() == 'Z'
+            self.name.ends_with('Z')
         } else {
-            self.name == "ZZZ".to_string()
+            self.name == "ZZZ"
         }
     }
 }
@@ -132,24 +162,10 @@ impl AllData {
         let mut move_seq: Vec<Direction> = vec![];
         for (idx, line) in input.iter().enumerate() {
             if idx == 0 {
-                move_seq = Direction::parse_seq(&line);
+                move_seq = Direction::parse_seq(line);
             } else if idx >= 2 {
-                let parts: Vec<String> = line.split('=').map(|k| k.to_string()).collect();
-                if parts.len() > 1 {
-                    let loc_name = parts[0].trim();
-                    let directions_parts: Vec<String> = parts[1]
-                        .replace(&['(', ')'], "")
-                        .split(',')
-                        .map(|k| k.to_string())
-                        .collect();
-                    if directions_parts.len() > 1 {
-                        let loc = Location {
-                            name: loc_name.into(),
-                            left: directions_parts[0].trim().into(),
-                            right: directions_parts[1].trim().into(),
-                        };
-                        loc_data_hm.insert(loc_name.into(), loc);
-                    }
+                if let Ok(loc) = parse_all(Location::parser(), line) {
+                    loc_data_hm.insert(loc.name.clone(), loc);
                 }
             }
         }
@@ -207,14 +223,19 @@ impl AllData {
      * out the cycle length of each ghost, then you are looking for the LCM of all the cycle
      * lengths. */
 
-    fn num_moves_2(&self) -> Result<u64, Box<dyn Error>> {
+    fn cycle_lengths(&self) -> Result<Vec<u64>, Box<dyn Error>> {
         let mut cycle_lengths: Vec<u64> = vec![];
         for k in self.get_start2() {
             cycle_lengths.push(self.num_moves(k.clone(), true)?);
         }
-        let mut out_val = cycle_lengths.pop().unwrap();
-        while !cycle_lengths.is_empty() {
-            out_val = lcm(out_val, cycle_lengths.pop().unwrap());
+        Ok(cycle_lengths)
+    }
+
+    fn num_moves_2(&self) -> Result<u64, Box<dyn Error>> {
+        let mut cycle_lengths = self.cycle_lengths()?;
+        let mut out_val = cycle_lengths.pop().ok_or(AdventError::new("no start!"))?;
+        while let Some(k) = cycle_lengths.pop() {
+            out_val = lcm(out_val, k).ok_or(AdventError::new("answer overflows u64"))?;
         }
         Ok(out_val)
     }
@@ -231,8 +252,27 @@ fn fast_euclid(a: u64, b: u64) -> u64 {
     v
 }
 
-fn lcm(a: u64, b: u64) -> u64 {
-    a * b / fast_euclid(a, b)
+// None if the LCM doesn't fit in a u64
+fn lcm(a: u64, b: u64) -> Option<u64> {
+    (a / fast_euclid(a, b)).checked_mul(b)
+}
+
+// Use u64 arithmetic for as long as it doesn't overflow, then switch to BigUint
+fn lcm_all_big(vals: &[u64]) -> BigUint {
+    let mut small: u64 = 1;
+    let mut pos: usize = 0;
+    while pos < vals.len() {
+        match lcm(small, vals[pos]) {
+            Some(k) => {
+                small = k;
+                pos += 1;
+            }
+            None => break,
+        }
+    }
+    vals[pos..]
+        .iter()
+        .fold(BigUint::from(small), |acc, k| acc.lcm(&BigUint::from(*k)))
 }
 
 #[cfg(test)]
@@ -279,7 +319,16 @@ XXX = (XXX, XXX)";
     fn part2_test() {
         if PART_2_IMPL {
             let example_input = common::split_string(EXAMPLE_INPUT_3.into());
-            assert_eq!(part_2(example_input).unwrap(), 6);
+            assert_eq!(part_2(example_input.clone()).unwrap(), 6);
+            assert_eq!(part_2_big(example_input).unwrap(), BigUint::from(6_u64));
         }
     }
+
+    #[test]
+    fn lcm_overflow_test() {
+        let vals: Vec<u64> = vec![4_294_967_291, 4_294_967_279, 3, 6];
+        assert_eq!(lcm(vals[0], vals[1]), Some(18446743979220271189));
+        assert_eq!(lcm(lcm(vals[0], vals[1]).unwrap(), vals[2]), None);
+        assert_eq!(lcm_all_big(&vals).to_string(), "110680463875321627134");
+    }
 }
@@ -7,6 +7,7 @@ use std::error::Error;
 use std::str::FromStr;
 
 use crate::common;
+use crate::common::checked;
 
 const DAY: usize = 9;
 const PART_1_IMPL: bool = true;
@@ -29,20 +30,26 @@ pub fn run() {
 }
 
 pub fn part_1(input: Vec<String>) -> Result<i64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 1);
     let line_vecs: Vec<Vec<i64>> = input.iter().map(|k| parse_line(k.clone())).collect();
     let line_sums: Vec<i64> = line_vecs.iter().map(|v| part_1_line(v.to_vec())).collect();
-    Ok(line_sums.iter().sum())
+    Ok(checked::sum(line_sums, "sum of extrapolated values"))
 }
 
 pub fn part_2(input: Vec<String>) -> Result<i64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 2);
     let line_vecs: Vec<Vec<i64>> = input.iter().map(|k| parse_line(k.clone())).collect();
     let line_sums: Vec<i64> = line_vecs.iter().map(|v| part_2_line(v.to_vec())).collect();
-    Ok(line_sums.iter().sum())
+    Ok(checked::sum(line_sums, "sum of extrapolated values"))
 }
 
 fn vec_diff(v: Vec<i64>) -> Option<Vec<i64>> {
-    if v.len() > 0 {
-        Some((0..(v.len() - 1)).map(|k| v[k + 1] - v[k]).collect())
+    if !v.is_empty() {
+        Some(
+            (0..(v.len() - 1))
+                .map(|k| checked::sub(v[k + 1], v[k], "vec_diff"))
+                .collect(),
+        )
     } else {
         None
     }
@@ -62,9 +69,8 @@ fn part_2_extrapol(v: Vec<Vec<i64>>) -> Vec<i64> {
     let mut last_val: i64 = 0;
     let mut v = v;
     let mut out_vec: Vec<i64> = vec![];
-    while !v.is_empty() {
-        let w = v.pop().unwrap();
-        last_val = w.get(0).unwrap() - last_val;
+    while let Some(w) = v.pop() {
+        last_val = checked::sub(*w.first().unwrap(), last_val, "part_2_extrapol");
         out_vec.push(last_val);
     }
     out_vec
@@ -82,7 +88,7 @@ fn part_1_line(v: Vec<i64>) -> i64 {
     match v.len() {
         0 => 0_i64,
         1 => *v.last().unwrap(),
-        _ => derive(v).iter().map(|k| k.last().unwrap()).sum(),
+        _ => checked::sum(derive(v).iter().map(|k| *k.last().unwrap()), "part_1_line"),
     }
 }
 
@@ -95,14 +101,11 @@ fn part_2_line(v: Vec<i64>) -> i64 {
 }
 
 fn parse_line(line: String) -> Vec<i64> {
-    line.split_whitespace()
-        .map(|k| i64::from_str(k))
-        .filter(|k| k.is_ok())
-        .map(|k| k.unwrap())
-        .collect()
+    line.split_whitespace().flat_map(i64::from_str).collect()
 }
 
 #[cfg(test)]
+#[allow(clippy::get_first)]
 mod tests {
     use super::*;
     use crate::common;
@@ -202,4 +205,12 @@ mod tests {
             assert_eq!(part_2(example_input).unwrap(), 2);
         }
     }
+
+    #[cfg(feature = "checked-arith")]
+    #[test]
+    #[should_panic(expected = "arithmetic overflow in day 9, part 1: vec_diff")]
+    fn part1_overflow_test() {
+        let example_input = vec![format!("{} 0 {}", i64::MIN, i64::MAX)];
+        part_1(example_input).unwrap();
+    }
 }
@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use std::error::Error;
 
 use crate::common;
+use crate::common::checked;
 use crate::common::AdventError;
 
 const DAY: usize = 10;
@@ -30,6 +31,7 @@ pub fn run() {
 }
 
 pub fn part_1(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 1);
     let board = Board::try_from(input)?;
     let loop_len = board.find_main_loop()?.len() - 1;
     Ok(loop_len as u64 / 2)
@@ -54,7 +56,7 @@ impl Direction {
     }
 
     // loc given in (col, row)
-    fn from_coords(&self, loc: (usize, usize)) -> Option<(usize, usize)> {
+    fn step_from(&self, loc: (usize, usize)) -> Option<(usize, usize)> {
         match self {
             Self::North => {
                 if loc.1 > 0 {
@@ -100,10 +102,7 @@ impl Direction {
     }
 
     fn adjacent_coords(loc: (usize, usize)) -> Vec<(usize, usize)> {
-        Self::iter_all()
-            .map(|k| k.from_coords(loc))
-            .filter_map(|k| k)
-            .collect()
+        Self::iter_all().filter_map(|k| k.step_from(loc)).collect()
     }
 
     fn iter_all() -> Box<dyn Iterator<Item = Direction>> {
@@ -255,43 +254,35 @@ impl Board {
             let mut next_dir = dir;
             let mut done = false;
             while !done {
-                if let Some(tile) = self.0.get(&cur_pos) {
-                    match tile {
-                        Tile::Start | Tile::Pipe(_) => {
-                            out_vec.push(cur_pos);
-                            if let Some(new_pos) = next_dir.from_coords(cur_pos) {
-                                if let Some(next_tile) = self.0.get(&new_pos) {
-                                    if next_tile
-                                        .possible_directions()
-                                        .iter()
-                                        .map(|k| k.opposite())
-                                        .filter(|k| *k == next_dir)
-                                        .count()
-                                        == 1
-                                    // This will be false if at a dead-end
-                                    {
-                                        cur_pos = new_pos;
-                                        if let Tile::Pipe(next_type) = next_tile {
-                                            next_dir = next_type
-                                                .other_direction(next_dir.opposite())
-                                                .unwrap();
-                                        } else {
-                                            out_vec.push(new_pos);
-                                            done = true; // We are back at start
-                                        }
-                                    } else {
-                                        done = true;
-                                    }
+                if let Some(Tile::Start | Tile::Pipe(_)) = self.0.get(&cur_pos) {
+                    out_vec.push(cur_pos);
+                    if let Some(new_pos) = next_dir.step_from(cur_pos) {
+                        if let Some(next_tile) = self.0.get(&new_pos) {
+                            if next_tile
+                                .possible_directions()
+                                .iter()
+                                .map(|k| k.opposite())
+                                .filter(|k| *k == next_dir)
+                                .count()
+                                == 1
+                            // This will be false if at a dead-end
+                            {
+                                cur_pos = new_pos;
+                                if let Tile::Pipe(next_type) = next_tile {
+                                    next_dir =
+                                        next_type.other_direction(next_dir.opposite()).unwrap();
                                 } else {
-                                    done = true;
+                                    out_vec.push(new_pos);
+                                    done = true; // We are back at start
                                 }
                             } else {
                                 done = true;
                             }
-                        }
-                        _ => {
+                        } else {
                             done = true;
                         }
+                    } else {
+                        done = true;
                     }
                 } else {
                     done = true;
@@ -349,6 +340,7 @@ impl Board {
 }
 
 pub fn part_2(input: Vec<String>) -> Result<u64, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 2);
     let board = Board::try_from(input)?;
     let main_loop = board.find_main_loop()?;
     Ok(count_inside(main_loop)? as u64)
@@ -416,7 +408,7 @@ fn double_path(pth: Vec<(usize, usize)>) -> Result<Vec<(usize, usize)>, AdventEr
         let dir: Direction = Direction::between_coords(start, end)
             .ok_or(AdventError::new("not in same row or column"))?;
         let new_start = double_coord(start);
-        let middle = dir.from_coords(new_start).unwrap();
+        let middle = dir.step_from(new_start).unwrap();
         out_vec.push(new_start);
         out_vec.push(middle);
     }
@@ -440,13 +432,12 @@ fn complement_component(pth: Vec<(usize, usize)>, loc: (usize, usize)) -> Vec<(u
         let se_corner = southeast_corner(pth.clone()).unwrap();
         let se_corner = (se_corner.0 + 1, se_corner.1 + 1);
         let mut to_process: Vec<(usize, usize)> = vec![loc];
-        while !to_process.is_empty() {
-            let cur_loc = to_process.pop().unwrap();
+        while let Some(cur_loc) = to_process.pop() {
             let mut adjacent: Vec<(usize, usize)> = Direction::adjacent_coords(cur_loc)
                 .iter()
-                .filter(|k| !pth.clone().contains(&k))
-                .filter(|k| !out_vec.clone().contains(&k))
-                .filter(|k| !to_process.clone().contains(&k))
+                .filter(|k| !pth.clone().contains(k))
+                .filter(|k| !out_vec.clone().contains(k))
+                .filter(|k| !to_process.clone().contains(k))
                 .copied()
                 .filter(|k| k.0 <= se_corner.0 && k.1 <= se_corner.1)
                 .collect();
@@ -475,6 +466,7 @@ fn count_inside(pth: Vec<(usize, usize)>) -> Result<usize, AdventError> {
 }
 
 #[cfg(test)]
+#[allow(clippy::get_first)]
 mod tests {
     use super::*;
     use crate::common;
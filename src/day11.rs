@@ -7,7 +7,9 @@ use std::error::Error;
 use std::num::NonZeroUsize;
 
 use crate::common;
+use crate::common::checked;
 use crate::common::AdventError;
+use crate::common::BigUint;
 
 const DAY: usize = 11;
 const PART_1_IMPL: bool = true;
@@ -22,7 +24,10 @@ pub fn run() {
         );
     }
     if PART_2_IMPL {
-        println!("{}", common::soln_output(DAY, 2, part_2(input).unwrap()));
+        println!(
+            "{}",
+            common::soln_output(DAY, 2, part_2_big(input).unwrap())
+        );
     }
     if !(PART_1_IMPL || PART_2_IMPL) {
         println!("Not implemented yet");
@@ -30,6 +35,7 @@ pub fn run() {
 }
 
 pub fn part_1(input: Vec<String>) -> Result<usize, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 1);
     Ok(distance_after_expansion(
         input,
         NonZeroUsize::new(2).unwrap(),
@@ -37,16 +43,47 @@ pub fn part_1(input: Vec<String>) -> Result<usize, Box<dyn Error>> {
 }
 
 pub fn part_2(input: Vec<String>) -> Result<usize, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 2);
     Ok(distance_after_expansion(
         input,
         NonZeroUsize::new(1_000_000).unwrap(),
     ))
 }
 
+/* Same as part_2, but falls back to arbitrary precision if the answer doesn't fit in a usize */
+pub fn part_2_big(input: Vec<String>) -> Result<BigUint, Box<dyn Error>> {
+    let _part = checked::solving(DAY, 2);
+    distance_after_expansion_big(input, &BigUint::from(1_000_000_u64))
+}
+
 fn distance_after_expansion(input: Vec<String>, factor: NonZeroUsize) -> usize {
     GalaxyData::from(input).expand(factor).total_distances()
 }
 
+/* Every empty row or column between two galaxies adds (factor - 1) to the distance between them,
+ * so rather than expanding, we can take the unexpanded total and add (factor - 1) times the
+ * number of times an empty row or column is crossed. */
+fn distance_after_expansion_big(
+    input: Vec<String>,
+    factor: &BigUint,
+) -> Result<BigUint, Box<dyn Error>> {
+    let gal_data = GalaxyData::from(input);
+    let base = gal_data.total_distances();
+    let crossings = gal_data.empty_crossings();
+    let expansion_add = factor
+        .checked_sub(&BigUint::one())
+        .ok_or(AdventError::new("expansion factor must be positive"))?;
+    let small_total = expansion_add
+        .to_u64()
+        .and_then(|k| usize::try_from(k).ok())
+        .and_then(|k| k.checked_mul(crossings))
+        .and_then(|k| k.checked_add(base));
+    Ok(match small_total {
+        Some(k) => BigUint::from(k),
+        None => &BigUint::from(base) + &(&expansion_add * &BigUint::from(crossings)),
+    })
+}
+
 /* The distance function being used in this problem is the Manhattan distance (a.k.a. taxi-cab
  * metric) */
 fn manhattan_distance(loc1: (usize, usize), loc2: (usize, usize)) -> usize {
@@ -100,12 +137,19 @@ impl GalaxyData {
         let empty_rows = self.empty_rows();
         let empty_cols = self.empty_cols();
         let expansion_add: usize = factor.get() - 1;
-        let width = self.width + expansion_add * empty_cols.len();
-        let height = self.height + expansion_add * empty_rows.len();
+        let shift = |val: usize, empty_before: usize| {
+            checked::add(
+                val,
+                checked::mul(expansion_add, empty_before, "expand"),
+                "expand",
+            )
+        };
+        let width = shift(self.width, empty_cols.len());
+        let height = shift(self.height, empty_rows.len());
         let mut galaxies: Vec<(usize, usize)> = vec![];
         for (x, y) in self.galaxies.iter() {
-            let new_x: usize = x + expansion_add * empty_cols.iter().filter(|k| *k < x).count();
-            let new_y: usize = y + expansion_add * empty_rows.iter().filter(|k| *k < y).count();
+            let new_x: usize = shift(*x, empty_cols.iter().filter(|k| *k < x).count());
+            let new_y: usize = shift(*y, empty_rows.iter().filter(|k| *k < y).count());
             galaxies.push((new_x, new_y));
         }
         Self {
@@ -127,12 +171,28 @@ impl GalaxyData {
         out_vec
     }
 
-    fn total_distances(&self) -> usize {
+    // Total number of empty rows and columns lying between pairs of galaxies
+    fn empty_crossings(&self) -> usize {
+        let empty_rows = self.empty_rows();
+        let empty_cols = self.empty_cols();
+        let between = |k: &usize, a: usize, b: usize| a.min(b) < *k && *k < a.max(b);
         self.galaxy_pairs()
             .iter()
-            .map(|(u, v)| manhattan_distance(*u, *v))
+            .map(|(u, v)| {
+                empty_cols.iter().filter(|k| between(k, u.0, v.0)).count()
+                    + empty_rows.iter().filter(|k| between(k, u.1, v.1)).count()
+            })
             .sum()
     }
+
+    fn total_distances(&self) -> usize {
+        checked::sum(
+            self.galaxy_pairs()
+                .iter()
+                .map(|(u, v)| manhattan_distance(*u, *v)),
+            "total_distances",
+        )
+    }
 }
 
 #[cfg(test)]
@@ -237,4 +297,19 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn distance_after_expansion_big_test() {
+        assert_eq!(
+            distance_after_expansion_big(get_example_input(), &BigUint::from(10_u64)).unwrap(),
+            BigUint::from(1030_u64)
+        );
+        assert_eq!(
+            distance_after_expansion_big(get_example_input(), &BigUint::from(10_u64).pow(30))
+                .unwrap()
+                .to_string(),
+            "82000000000000000000000000000210"
+        );
+        assert!(distance_after_expansion_big(get_example_input(), &BigUint::zero()).is_err());
+    }
 }
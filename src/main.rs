@@ -8,16 +8,39 @@
 use std::env;
 
 use cphan_advent_2023::all_days;
+use cphan_advent_2023::day02;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     if args.len() < 2 {
-        println!("Usage: {} [day_numbers]", args[0]);
+        println!(
+            "Usage: {} [--explain [--no-color | --json | --csv]] [--bag=\"12 red, 13 green, 14 blue\"] [day_numbers]",
+            args[0]
+        );
+        println!("       {} normalize [day_2_input_file]", args[0]);
+    } else if args[1] == "normalize" {
+        if let Err(e) = day02::run_normalize(args.get(2).map(|k| k.as_str())) {
+            println!("Could not normalize: {}", e);
+        }
     } else {
+        let explain = args.iter().any(|k| k == "--explain");
+        let options = all_days::Options {
+            bag: args
+                .iter()
+                .find_map(|k| k.strip_prefix("--bag="))
+                .map(|k| k.to_string()),
+            no_color: args.iter().any(|k| k == "--no-color"),
+            json: args.iter().any(|k| k == "--json"),
+            csv: args.iter().any(|k| k == "--csv"),
+        };
         for val in args {
             if let Ok(d) = val.parse::<usize>() {
-                all_days::run_day(d)
+                if explain {
+                    all_days::explain_day(d, &options)
+                } else {
+                    all_days::run_day_with(d, &options)
+                }
             }
         }
     }